@@ -3,21 +3,292 @@ use dotenvy::dotenv;
 use std::env;
 use std::fs::File;
 use std::io::Write;
+use url::Url;
+
+/// Variables the config is read from, the legacy names still work as a fallback
+const SUPABASE_URL: [&str; 2] = ["SUPABASE_URL", "APP_SUPABASE_URL"];
+const SUPABASE_ANON_KEY: [&str; 2] = ["SUPABASE_ANON_KEY", "APP_API_KEY"];
+const REDIRECT_URL: &str = "APP_REDIRECT_URL";
+const OAUTH_PROVIDERS: &str = "APP_OAUTH_PROVIDERS";
+const PASSWORD_POLICY: &str = "APP_PASSWORD_POLICY";
+const IDLE_TIMEOUT_MINUTES: &str = "APP_IDLE_TIMEOUT_MINUTES";
+const PROFILE: &str = "APP_PROFILE";
+
+/// Builds without a profile are production builds
+const DEFAULT_PROFILE: &str = "prod";
+
+/// Rules `PasswordPolicy` (src/core/password.rs) knows besides `min_length=<n>`
+const PASSWORD_RULES: [&str; 5] = ["lowercase", "uppercase", "digits", "symbols", "no_email"];
+/// Names `OAuthProvider` (src/core/providers.rs) is parsed from
+const OAUTH_PROVIDER_NAMES: [&str; 15] = [
+  "apple",
+  "azure",
+  "bitbucket",
+  "discord",
+  "facebook",
+  "github",
+  "gitlab",
+  "google",
+  "linkedin",
+  "linkedin_oidc",
+  "slack",
+  "slack_oidc",
+  "spotify",
+  "twitch",
+  "twitter",
+];
+
+struct BuildConfig {
+  profile: String,
+  supabase_url: Url,
+  anon_key: String,
+  redirect_url: Url,
+  oauth_providers: String,
+  password_policy: String,
+  idle_timeout_minutes: u32,
+}
 
 fn main() {
   println!("cargo:rerun-if-changed=.env");
-  let dest_path = "./src/env.rs";
-  let mut f = File::create(&dest_path).unwrap();
-
-  // use the dotenv crate to get the .env values
-  dotenv().expect("Envirament variables are not provided");
-  f.write_all(b"// This file is automatically generated by build.rs\n\n").unwrap();
-  f.write_all(b"#![allow(unused)]\n\n").unwrap();
-  for (key, value) in env::vars() {
-    if key.starts_with("APP_") {
-      let line = format!("pub const {}: &'static str = \"{}\";\n", key, value.replace("\"", "\\\""));
-      f.write_all(line.as_bytes()).unwrap();
+  for key in SUPABASE_URL.iter().chain(&SUPABASE_ANON_KEY).chain(&[
+    REDIRECT_URL,
+    OAUTH_PROVIDERS,
+    PASSWORD_POLICY,
+    IDLE_TIMEOUT_MINUTES,
+    PROFILE,
+    "APP_DATABASE_URL",
+  ]) {
+    println!("cargo:rerun-if-env-changed={key}");
+  }
+
+  // use the dotenv crate to get the .env values, they can come from the environment too (e.g. CI).
+  // Variables that are already set are kept, so the environment wins over `.env.<profile>`,
+  // which wins over `.env`.
+  let profile = match read_profile() {
+    Ok(profile) => profile,
+    Err(err) => fail(vec![err]),
+  };
+  dotenv().ok();
+  let config = match read_config(profile) {
+    Ok(config) => config,
+    Err(errors) => fail(errors),
+  };
+  write_config(&config);
+}
+
+fn fail(errors: Vec<String>) -> ! {
+  eprintln!("Invalid build configuration, check your .env files:");
+  for err in errors {
+    eprintln!("  - {err}");
+  }
+  std::process::exit(1);
+}
+
+/// `APP_PROFILE` from the environment or `.env`, loading `.env.<profile>` when one is chosen
+fn read_profile() -> Result<String, String> {
+  let profile = env::var(PROFILE).ok().or_else(|| {
+    dotenvy::from_filename_iter(".env")
+      .ok()?
+      .flatten()
+      .find_map(|(key, value)| (key == PROFILE).then_some(value))
+  });
+  let Some(profile) = profile.map(|f| f.trim().to_string()).filter(|f| !f.is_empty()) else {
+    return Ok(DEFAULT_PROFILE.to_string());
+  };
+  if !profile.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+    return Err(format!("`{PROFILE}` must be a name like dev, staging or prod, got `{profile}`"));
+  }
+  let path = format!(".env.{profile}");
+  println!("cargo:rerun-if-changed={path}");
+  match dotenvy::from_filename(&path) {
+    Ok(_) => Ok(profile),
+    // The default profile works without its own file
+    Err(err) if err.not_found() && profile == DEFAULT_PROFILE => Ok(profile),
+    Err(err) if err.not_found() => Err(format!("`{PROFILE}` is `{profile}`, `{path}` is missing")),
+    Err(err) => Err(format!("Can't read `{path}`: {err}")),
+  }
+}
+
+fn var(keys: &[&str]) -> Option<String> {
+  keys.iter().find_map(|key| env::var(key).ok()).map(|value| value.trim().to_string())
+}
+
+fn read_config(profile: String) -> Result<BuildConfig, Vec<String>> {
+  let mut errors = Vec::new();
+
+  let supabase_url = match var(&SUPABASE_URL) {
+    None => Err(format!("`{}` is required, e.g. https://[ref].supabase.co", SUPABASE_URL[0])),
+    Some(value) => parse_url(SUPABASE_URL[0], &value).and_then(|url| {
+      if url.path() != "/" || url.query().is_some() || url.fragment().is_some() {
+        Err(format!("`{}` must be the project URL, got `{value}`", SUPABASE_URL[0]))
+      } else {
+        Ok(url)
+      }
+    }),
+  };
+  let anon_key = match var(&SUPABASE_ANON_KEY) {
+    Some(value) if !value.is_empty() => Ok(value),
+    _ => Err(format!("`{}` is required", SUPABASE_ANON_KEY[0])),
+  };
+  let redirect_url = match var(&[REDIRECT_URL]) {
+    None => Err(format!("`{REDIRECT_URL}` is required, e.g. https://example.com/redirect")),
+    Some(value) => parse_url(REDIRECT_URL, &value),
+  };
+  let idle_timeout_minutes = match var(&[IDLE_TIMEOUT_MINUTES]).unwrap_or_default().as_str() {
+    "" => Ok(0),
+    value => value
+      .parse::<u32>()
+      .map_err(|_| format!("`{IDLE_TIMEOUT_MINUTES}` must be a number of minutes, got `{value}`")),
+  };
+  let oauth_providers = check_oauth_providers(&var(&[OAUTH_PROVIDERS]).unwrap_or_default());
+  let password_policy = check_password_policy(&var(&[PASSWORD_POLICY]).unwrap_or_default());
+
+  if let (Ok(supabase_url), Some(database_url)) = (&supabase_url, var(&["APP_DATABASE_URL"])) {
+    let derived = endpoint(supabase_url, "rest/v1");
+    if database_url.trim_end_matches('/') != derived {
+      println!("cargo:warning=APP_DATABASE_URL is ignored, the REST endpoint is {derived}");
     }
   }
+
+  let supabase_url = supabase_url.map_err(|err| errors.push(err)).ok();
+  let anon_key = anon_key.map_err(|err| errors.push(err)).ok();
+  let redirect_url = redirect_url.map_err(|err| errors.push(err)).ok();
+  let idle_timeout_minutes = idle_timeout_minutes.map_err(|err| errors.push(err)).ok();
+  let oauth_providers = oauth_providers.map_err(|err| errors.push(err)).ok();
+  let password_policy = password_policy.map_err(|err| errors.push(err)).ok();
+  match (
+    supabase_url,
+    anon_key,
+    redirect_url,
+    idle_timeout_minutes,
+    oauth_providers,
+    password_policy,
+  ) {
+    (
+      Some(supabase_url),
+      Some(anon_key),
+      Some(redirect_url),
+      Some(idle_timeout_minutes),
+      Some(oauth_providers),
+      Some(password_policy),
+    ) => Ok(BuildConfig {
+      profile,
+      supabase_url,
+      anon_key,
+      redirect_url,
+      oauth_providers,
+      password_policy,
+      idle_timeout_minutes,
+    }),
+    _ => Err(errors),
+  }
+}
+
+/// Comma separated provider names, e.g. `google,github`
+fn check_oauth_providers(value: &str) -> Result<String, String> {
+  let unknown: Vec<_> = value
+    .split(',')
+    .map(|f| f.trim())
+    .filter(|f| !f.is_empty() && !OAUTH_PROVIDER_NAMES.contains(&f.to_lowercase().as_str()))
+    .collect();
+  if unknown.is_empty() {
+    Ok(value.to_string())
+  } else {
+    Err(format!(
+      "`{OAUTH_PROVIDERS}` has unknown providers `{}`, known are {}",
+      unknown.join(","),
+      OAUTH_PROVIDER_NAMES.join(", ")
+    ))
+  }
 }
 
+/// Comma separated rules, e.g. `min_length=8,lowercase,digits`
+fn check_password_policy(value: &str) -> Result<String, String> {
+  let invalid: Vec<_> = value
+    .split(',')
+    .map(|f| f.trim())
+    .filter(|rule| match rule.split_once('=') {
+      Some(("min_length", min)) => min.trim().parse::<usize>().is_err(),
+      Some(_) => true,
+      None => !rule.is_empty() && !PASSWORD_RULES.contains(rule),
+    })
+    .collect();
+  if invalid.is_empty() {
+    Ok(value.to_string())
+  } else {
+    Err(format!(
+      "`{PASSWORD_POLICY}` has invalid rules `{}`, known are min_length=<n>, {}",
+      invalid.join(","),
+      PASSWORD_RULES.join(", ")
+    ))
+  }
+}
+
+fn parse_url(key: &str, value: &str) -> Result<Url, String> {
+  match Url::parse(value) {
+    Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => Ok(url),
+    Ok(_) => Err(format!("`{key}` must be an http(s) URL, got `{value}`")),
+    Err(err) => Err(format!("`{key}` is not a valid URL ({err}), got `{value}`")),
+  }
+}
+
+/// `https://[project_ref].supabase.co` + `auth/v1`, the project URL has no path to replace
+/// `path` below the project URL, which may have a path itself, e.g. behind a proxy
+fn endpoint(supabase_url: &Url, path: &str) -> String {
+  let base = format!("{}/", supabase_url.as_str().trim_end_matches('/'));
+  let url = Url::parse(&base).and_then(|f| f.join(path)).expect("Can't derive the endpoint");
+  url.as_str().trim_end_matches('/').to_string()
+}
+
+fn write_config(config: &BuildConfig) {
+  let dest_path = "./src/env.rs";
+  let mut f = File::create(dest_path).unwrap();
+  let supabase_url = config.supabase_url.as_str().trim_end_matches('/');
+  // `{:?}` escapes the values as Rust string literals
+  let source = format!(
+    r#"// This file is automatically generated by build.rs
+
+#![allow(unused)]
+
+/// Settings built into the app, validated by build.rs. The endpoints are derived from
+/// `supabase_url`.
+pub struct BuildConfig {{
+    /// `APP_PROFILE`, e.g. `dev`, `staging` or `prod`
+    pub profile: &'static str,
+    pub supabase_url: &'static str,
+    pub auth_url: &'static str,
+    pub rest_url: &'static str,
+    pub storage_url: &'static str,
+    pub anon_key: &'static str,
+    pub redirect_url: &'static str,
+    pub oauth_providers: &'static str,
+    pub password_policy: &'static str,
+    pub idle_timeout_minutes: u32,
+}}
+
+pub const BUILD_CONFIG: BuildConfig = BuildConfig {{
+    profile: {:?},
+    supabase_url: {:?},
+    auth_url: {:?},
+    rest_url: {:?},
+    storage_url: {:?},
+    anon_key: {:?},
+    redirect_url: {:?},
+    oauth_providers: {:?},
+    password_policy: {:?},
+    idle_timeout_minutes: {},
+}};
+"#,
+    config.profile,
+    supabase_url,
+    endpoint(&config.supabase_url, "auth/v1"),
+    endpoint(&config.supabase_url, "rest/v1"),
+    endpoint(&config.supabase_url, "storage/v1"),
+    config.anon_key,
+    config.redirect_url.as_str(),
+    config.oauth_providers,
+    config.password_policy,
+    config.idle_timeout_minutes,
+  );
+  f.write_all(source.as_bytes()).unwrap();
+}
@@ -1,19 +1,25 @@
-use std::ops::Not;
 use std::time::Duration;
 
 use leptos::*;
 
 use leptos_router::*;
-use leptos_use::{utils::JsonCodec,storage::{use_local_storage,}};
 
-use crate::components::{Home, LogIn, SignUp};
-use crate::core::helper::{local_storage, url_hash_to_user};
-use crate::core::models::User;
+use crate::components::{
+    AuthRedirect, Home, IdleTimeout, LogIn, MfaChallenge, MfaEnroll, ProfileBadge, RequireAuth,
+    ResetPassword, Settings, SignUp, SignUpConfirmation, VerifyOtp,
+};
+use crate::core::{
+    auth_service::provide_auth_service,
+    config::AppConfig,
+    helper::next_path,
+    routes::{base_path, AppRoute},
+};
 
 #[component]
-pub fn App() -> impl IntoView {
+pub fn App(config: AppConfig) -> impl IntoView {
     
-    let (user, set_user, _) = use_local_storage::<User, JsonCodec>("user");
+    provide_context(config);
+    let auth = provide_auth_service();
     let show_toast = RwSignal::new(false);
     let toast_text = RwSignal::new(String::new());
     provide_context(Callback::new(move |text: String| {
@@ -23,79 +29,133 @@ pub fn App() -> impl IntoView {
     }));
     
     view! {
-        <Router>
-            <Routes base="/csr-example-leptos-supabase".to_string()>
-                <Route
-                    path="/login"
-                    view=move || {
-                        if user.get_untracked().access_token.is_empty() {
-                            view! { <LogIn user=user set_user=set_user/> }
-                        } else {
-                            view! { <Redirect path="/csr-example-leptos-supabase/"/> }
+        <Show when=move || auth.loaded()>
+            <Router>
+                <Routes base=base_path()>
+                    <Route
+                        path=AppRoute::Login.path()
+                        view=move || {
+                            if auth.user_untracked().is_none() {
+                                view! { <LogIn/> }
+                            } else {
+                                view! { <Redirect path=next_path()/> }
+                            }
                         }
-                    }
-                />
-
-                <Route path="/signup" view=SignUp/>
-
-                <Route
-                    path="/redirect"
-                    view={move || {
-                        let new_user = url_hash_to_user(use_location().hash.get());
-                        match new_user {
-                            Some(new_user) => {
-                                if user.get_untracked().uuid != new_user.uuid {
-                                    local_storage().clear().expect("Can't access to local storage");
-                                }
-                                set_user.set(new_user);
-                                view! { <Redirect path="/csr-example-leptos-supabase/"/> }
+                    />
+
+                    <Route
+                        path=AppRoute::SignUp.path()
+                        view=move || view! { <SignUp/> }
+                    />
+
+                    <Route
+                        path=AppRoute::Verify.path()
+                        view=move || view! { <VerifyOtp/> }
+                    />
+
+                    <Route
+                        path=AppRoute::ResetPassword.path()
+                        view=move || view! { <ResetPassword/> }
+                    />
+
+                    <Route
+                        path=AppRoute::Redirect.path()
+                        view=move || view! { <AuthRedirect/> }
+                    />
+
+                    <Route
+                        path=AppRoute::MfaChallenge.path()
+                        view=move || {
+                            view! {
+                                <RequireAuth allow_aal1=true>
+                                    <MfaChallenge/>
+                                </RequireAuth>
                             }
-                            /// (Some(access_token), None) => _Never happens! This path is for login from google so both tokens always are provided
-                            _ => view! { <Redirect path="/csr-example-leptos-supabase/login"/> },
                         }
-                    }}
-                />
-
-                <Route
-                    path="/"
-                    view=move || {
-                        if user.get_untracked().access_token.is_empty().not() {
-                            view! { <Home user=user set_user=set_user/> }
-                        } else {
-                            view! { <Redirect path="/csr-example-leptos-supabase/login"/> }
+                    />
+
+                    <Route
+                        path=AppRoute::MfaEnroll.path()
+                        view=move || {
+                            view! {
+                                <RequireAuth allow_aal1=true>
+                                    <MfaEnroll/>
+                                </RequireAuth>
+                            }
                         }
-                    }
-                />
-
-                <Route
-                    path="/signup/confirmation"
-                    view=move || {
-                        view! {
-                            <div style="position: absolute; top: 50%; left: 50%; transform: translate(-50%, -50%);font-size:22px;">
-                                "Please confirm your email from your inbox"
-                            </div>
+                    />
+
+                    <Route
+                        path=AppRoute::Home.path()
+                        view=move || {
+                            view! {
+                                <RequireAuth>
+                                    <Home/>
+                                </RequireAuth>
+                            }
                         }
-                    }
-                />
-
-                <Route
-                    path="/*"
-                    view=move || {
-                        view! {
-                            // view! { <LogIn user=user set_user=set_user/> }
-                            <div style="position: absolute; top: 50%; left: 50%; transform: translate(-50%, -50%);font-size:22px;">
-                                "Page Not Found :("
-                            </div>
+                    />
+
+                    <Route
+                        path=AppRoute::Settings.path()
+                        view=move || {
+                            view! {
+                                <RequireAuth>
+                                    <Settings/>
+                                </RequireAuth>
+                            }
                         }
-                    }
-                />
+                    />
 
-            </Routes>
-            <div id="toast" class:show=show_toast>
-                {toast_text}
-            </div>
+                    <Route path=AppRoute::SignUpConfirmation.path() view=SignUpConfirmation/>
 
-        </Router>
+                    <Route
+                        path=AppRoute::RecoverConfirmation.path()
+                        view=move || {
+                            view! {
+                                <div style="position: absolute; top: 50%; left: 50%; transform: translate(-50%, -50%);font-size:22px;">
+                                    "Please check your inbox for the password reset link"
+                                </div>
+                            }
+                        }
+                    />
+
+                    <Route
+                        path="/*"
+                        view=move || {
+                            view! {
+                                // view! { <LogIn/> }
+                                <div style="position: absolute; top: 50%; left: 50%; transform: translate(-50%, -50%);font-size:22px;">
+                                    "Page Not Found :("
+                                </div>
+                            }
+                        }
+                    />
+
+                </Routes>
+                <Show when=move || auth.is_logged_in()>
+                    <IdleTimeout/>
+                </Show>
+                <div id="toast" class:show=show_toast>
+                    {toast_text}
+                </div>
+
+            </Router>
+        </Show>
+        <ProfileBadge/>
+    }
+}
+
+/// Shown instead of the app when the deployed `config.json` can't be used
+#[component]
+pub fn ConfigError(error: String) -> impl IntoView {
+    view! {
+        <div id="login-container">
+            <div id="login-signup-form">
+                <h1>Invalid Configuration</h1>
+                <p style="white-space: pre-line;">{error}</p>
+            </div>
+        </div>
     }
 }
 
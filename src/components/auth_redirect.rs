@@ -0,0 +1,100 @@
+use leptos::*;
+use leptos_router::*;
+
+use crate::core::{
+    auth_service::use_auth,
+    helper::{next_path, restore_remember_me, url_hash_to_user, url_params},
+    models::{RedirectError, User},
+    pkce,
+    routes::AppRoute,
+};
+
+/// Login page showing why the redirect failed
+fn login_path_with_error(message: &str) -> String {
+    AppRoute::Login.href_with(&[("error_description", message)])
+}
+
+/// Settings page showing the outcome of an email change link
+fn settings_path_with_message(message: &str) -> String {
+    AppRoute::Settings.href_with(&[("message", message)])
+}
+
+/// Where OAuth providers and auth email links send the user back to with the session
+#[component]
+pub fn AuthRedirect() -> impl IntoView {
+    let auth = use_auth();
+    let mut query = url_params(&use_location().search.get_untracked());
+    restore_remember_me(&query);
+
+    if let Some(err) = RedirectError::from_params(&mut query) {
+        return view! { <Redirect path=login_path_with_error(&err.to_string())/> };
+    }
+
+    match query.remove("code") {
+        // PKCE flow: the session has to be requested with the code and the stored verifier
+        Some(auth_code) => {
+            auth.authenticating();
+            spawn_local(async move {
+                let Some(code_verifier) = pkce::take_code_verifier() else {
+                    auth.sign_in_failed();
+                    use_navigate()(
+                        &login_path_with_error("The login was started in another browser"),
+                        Default::default(),
+                    );
+                    return;
+                };
+                let res = auth
+                    .client()
+                    .exchange_code_for_session(&auth_code, &code_verifier)
+                    .await
+                    .and_then(User::try_from);
+                match res {
+                    Ok(new_user) => {
+                        auth.sign_in(new_user);
+                        use_navigate()(&next_path(), Default::default());
+                    }
+                    Err(err) => {
+                        auth.sign_in_failed();
+                        use_navigate()(
+                            &login_path_with_error(&format!("Login Failed. {err}")),
+                            Default::default(),
+                        );
+                    }
+                }
+            });
+            view! {
+                <div style="position: absolute; top: 50%; left: 50%; transform: translate(-50%, -50%);font-size:22px;">
+                    "Signing in..."
+                </div>
+            }
+            .into_view()
+        }
+        // Implicit flow: the session (or the error) is in the URL hash
+        None => {
+            let url_hash = use_location().hash.get_untracked();
+            let hash_params = url_params(&url_hash);
+            // The first of the two email change links only returns a message
+            if let Some(message) = hash_params.get("message") {
+                if !hash_params.contains_key("access_token") {
+                    return view! { <Redirect path=settings_path_with_message(message)/> };
+                }
+            }
+            let email_changed = hash_params.get("type").is_some_and(|t| t == "email_change");
+            match url_hash_to_user(url_hash) {
+                Ok(new_user) => {
+                    auth.sign_in(new_user);
+                    if email_changed {
+                        view! {
+                            <Redirect path=settings_path_with_message(
+                                "Your email has been changed",
+                            )/>
+                        }
+                    } else {
+                        view! { <Redirect path=next_path()/> }
+                    }
+                }
+                Err(err) => view! { <Redirect path=login_path_with_error(&err.to_string())/> },
+            }
+        }
+    }
+}
@@ -8,38 +8,33 @@ use leptos_router::*;
 use leptos_use::storage::use_local_storage;
 use leptos_use::utils::JsonCodec;
 
-use postgrest::Postgrest;
 use serde_json::json;
 use web_sys::MouseEvent;
 
-use crate::{
-    core::{
-        helper::{local_storage, refresh_token},
-        models::{Company, Job, RefreshTokenError, Status, User},
-    },
-    env,
+use crate::core::{
+    auth_service::use_auth,
+    config::use_config,
+    helper::postgrest_client,
+    models::{Company, Job, LogoutScope, Status, User},
+    routes::AppRoute,
 };
 
 #[component]
-pub fn Home(user: Signal<User>, set_user: WriteSignal<User>) -> impl IntoView {
-    let postgrest_client = StoredValue::new(
-        Postgrest::new(env::APP_DATABASE_URL).insert_header("apikey", env::APP_API_KEY),
-    );
+pub fn Home() -> impl IntoView {
+    let auth = use_auth();
+    let postgrest_client = StoredValue::new(postgrest_client(&use_config()));
     let (companies, set_companies, _) = use_local_storage::<Vec<Company>, JsonCodec>("companies");
 
     let update_companies = move |company: &Company| {
         // To trigger companies signal to update
         batch(|| {
             set_companies.update(|f| {
-                f.iter_mut().filter(|c| c.date_added == company.date_added).next().and_then(
-                    |res| {
-                        res.name.set(company.name.get());
-                        res.phone.set(company.phone.get());
-                        res.jobs.set(company.jobs.get());
-                        res.status.set(company.status.get());
-                        Some(res)
-                    },
-                );
+                if let Some(res) = f.iter_mut().find(|c| c.date_added == company.date_added) {
+                    res.name.set(company.name.get());
+                    res.phone.set(company.phone.get());
+                    res.jobs.set(company.jobs.get());
+                    res.status.set(company.status.get());
+                }
             });
         });
     };
@@ -76,7 +71,7 @@ pub fn Home(user: Signal<User>, set_user: WriteSignal<User>) -> impl IntoView {
 
     let sync_insert_to_database = move |company: Company| async move {
         let postgrest_client = postgrest_client.get_value();
-        let user = user.get();
+        let user = auth.user_untracked().unwrap_or_default();
         company.status.set(Status::SyncingInsert);
         update_companies(&company); // To trigger companies signal to update
         let response = postgrest_client
@@ -115,7 +110,7 @@ pub fn Home(user: Signal<User>, set_user: WriteSignal<User>) -> impl IntoView {
     };
     let sync_edit_to_database = move |company: Company| async move {
         let postgrest_client = postgrest_client.get_value();
-        let user = user.get();
+        let user = auth.user_untracked().unwrap_or_default();
         company.status.set(Status::SyncingEdit);
         update_companies(&company); // To trigger companies signal to update
         let response = postgrest_client
@@ -150,7 +145,7 @@ pub fn Home(user: Signal<User>, set_user: WriteSignal<User>) -> impl IntoView {
     };
     let sync_delete_to_database = move |company: Company| async move {
         let postgrest_client = postgrest_client.get_value();
-        let user = user.get();
+        let user = auth.user_untracked().unwrap_or_default();
         company.status.set(Status::SyncingDelete);
         update_companies(&company); // To trigger companies signal to update
         let response = postgrest_client
@@ -186,9 +181,8 @@ pub fn Home(user: Signal<User>, set_user: WriteSignal<User>) -> impl IntoView {
     };
 
     let logout = move || {
-        local_storage().clear().expect("Can't access to local storage");
-        set_user.set(User::default());
-        use_navigate()("/csr-example-leptos-supabase/login", Default::default());
+        auth.log_out(LogoutScope::Local);
+        use_navigate()(&AppRoute::Login.href(), Default::default());
     };
     let retry_all_faileds = move || {
         spawn_local(async move {
@@ -230,117 +224,88 @@ pub fn Home(user: Signal<User>, set_user: WriteSignal<User>) -> impl IntoView {
         let response = postgrest_client
             .get_value()
             .from("companies")
-            .auth(user.get().access_token)
+            .auth(auth.with_user_untracked(|user| user.access_token.clone()).unwrap_or_default())
             // .eq("user_id", auth.get_value().uuid) // It is handled on the RLS side
             .select("date_added,name,phone,jobs")
             .order("date_added")
             .execute()
             .await;
-        match response {
-            Ok(response) => {
-                if response.status().is_success() {
-                    let comps = response
-                        .bytes()
-                        .await
-                        .ok()
-                        .map(|bytes| {
-                            serde_json::from_str::<Vec<Company>>(
-                                format!("{}", String::from_utf8(bytes.to_vec()).unwrap()).as_str(),
-                            )
-                            .ok()
-                        })
-                        .flatten();
-                    match comps {
-                        Some(new_companies) => {
-                            batch(|| {
-                                set_companies.update(move |current_companies| {
-                                    for new_company in new_companies.iter() {
-                                        // Adding the items have created in other devices
-                                        if current_companies.iter().any(|current_company| {
-                                            current_company.date_added == new_company.date_added
-                                        }) == false
-                                        {
-                                            current_companies.push(new_company.clone());
-                                        }
-                                        // Replace the same items have edited in other devices AFTER us
-                                        // And add the items that were about to insert/edit but the tab was closed
-                                        // and the signal wasn't updated but the insert/edit request was sent by the browser. Yes Chrome keeps requests alive.)
-                                        if let Some(replacing_company_index) =
-                                            current_companies.iter().position(|current_company| {
-                                                new_company.date_added == current_company.date_added
-                                                    && (current_company.status.get()
-                                                        == Status::Synced
-                                                        || current_company.status.get()
-                                                            == Status::SyncingInsert
-                                                        || current_company.status.get()
-                                                            == Status::SyncingEdit)
-                                            })
-                                        {
-                                            // Changed and refresh the reactive subfiealds
-                                            // The whole companies will be updated too because we are in set_companies.update !
-                                            current_companies[replacing_company_index]
-                                                .name
-                                                .set(new_company.name.get());
-                                            current_companies[replacing_company_index]
-                                                .phone
-                                                .set(new_company.phone.get());
-                                            current_companies[replacing_company_index]
-                                                .jobs
-                                                .set(new_company.jobs.get());
-                                            current_companies[replacing_company_index]
-                                                .status
-                                                .set(new_company.status.get());
-                                            // current_companies.remove(replacing_company_index);
-                                            // current_companies.push(new_company.clone());
-                                        }
-                                    }
-                                    // Delete the items has have been removed by other devices (Including the ones that were about to be deleted but the tab was closed
-                                    // and the signal wasn't updated but the delete request was sent by the browser. Yes Chrome keeps requests alive.)
-                                    current_companies.retain(|current_company| {
-                                        new_companies.iter().any(|new_company| {
-                                            new_company.date_added == current_company.date_added
-                                        }) || (current_company.status.get() != Status::Synced
-                                            && current_company.status.get()
-                                                != Status::SyncingDelete)
-                                    });
-                                    current_companies.sort_by_key(|f| f.date_added);
-                                });
-                            });
-                            retry_all_faileds();
-                        }
-                        None => {}
+        let Ok(response) = response else {
+            return;
+        };
+        if !response.status().is_success() {
+            if response.status().as_u16() == 401 {
+                access_token_expired.set(true);
+            }
+            return;
+        }
+        let Some(new_companies) = response
+            .bytes()
+            .await
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Vec<Company>>(&bytes).ok())
+        else {
+            return;
+        };
+        batch(|| {
+            set_companies.update(move |current_companies| {
+                for new_company in new_companies.iter() {
+                    // Adding the items have created in other devices
+                    if !current_companies
+                        .iter()
+                        .any(|current_company| current_company.date_added == new_company.date_added)
+                    {
+                        current_companies.push(new_company.clone());
                     }
-                } else {
-                    if response.status().as_u16() == 401 {
-                        access_token_expired.set(true);
+                    // Replace the same items have edited in other devices AFTER us
+                    // And add the items that were about to insert/edit but the tab was closed
+                    // and the signal wasn't updated but the insert/edit request was sent by the browser. Yes Chrome keeps requests alive.)
+                    if let Some(replacing_company_index) =
+                        current_companies.iter().position(|current_company| {
+                            new_company.date_added == current_company.date_added
+                                && (current_company.status.get() == Status::Synced
+                                    || current_company.status.get() == Status::SyncingInsert
+                                    || current_company.status.get() == Status::SyncingEdit)
+                        })
+                    {
+                        // Changed and refresh the reactive subfiealds
+                        // The whole companies will be updated too because we are in set_companies.update !
+                        current_companies[replacing_company_index].name.set(new_company.name.get());
+                        current_companies[replacing_company_index]
+                            .phone
+                            .set(new_company.phone.get());
+                        current_companies[replacing_company_index].jobs.set(new_company.jobs.get());
+                        current_companies[replacing_company_index]
+                            .status
+                            .set(new_company.status.get());
+                        // current_companies.remove(replacing_company_index);
+                        // current_companies.push(new_company.clone());
                     }
                 }
-            }
-            Err(_) => {}
-        }
+                // Delete the items has have been removed by other devices (Including the ones that were about to be deleted but the tab was closed
+                // and the signal wasn't updated but the delete request was sent by the browser. Yes Chrome keeps requests alive.)
+                current_companies.retain(|current_company| {
+                    new_companies
+                        .iter()
+                        .any(|new_company| new_company.date_added == current_company.date_added)
+                        || (current_company.status.get() != Status::Synced
+                            && current_company.status.get() != Status::SyncingDelete)
+                });
+                current_companies.sort_by_key(|f| f.date_added);
+            });
+        });
+        retry_all_faileds();
     };
     Effect::new(move |previous| {
         // To ignore incoming access_token_expired updates when it's already working on it
-        if access_token_expired.get() && previous.map(|f| f == false).unwrap_or(true) {
+        if access_token_expired.get() && previous != Some(true) {
             spawn_local(async move {
-                let res = refresh_token(user).await;
-
-                match res {
-                    Ok(new_user) => {
-                        set_user.set(new_user);
-                        spawn_local(async move {
-                            init_fetch().await;
-                        });
-                    }
-                    Err(err) => match err {
-                        RefreshTokenError::NetworkError => {}
-                        RefreshTokenError::JsonParseError => {}
-                        RefreshTokenError::RefreshTokenExpirationError => {
-                            set_user.set(User::default());
-                            use_navigate()("/csr-example-leptos-supabase/login", Default::default())
-                        }
-                        RefreshTokenError::UnknownError => {}
-                    },
+                // A failed refresh is retried by `AuthService`, an expired session is sent to
+                // the login page by `RequireAuth`
+                if auth.refresh().await.is_ok() {
+                    spawn_local(async move {
+                        init_fetch().await;
+                    });
                 }
                 access_token_expired.set(false);
             })
@@ -356,7 +321,41 @@ pub fn Home(user: Signal<User>, set_user: WriteSignal<User>) -> impl IntoView {
 
             <div id="main-column">
                 <div id="user-info">
-                    <h1 id="email">{move || user.get().email}</h1>
+                    <div id="avatar">{move || auth.with_user(User::initials)}</div>
+                    <div id="user-details">
+                        <h1 id="display-name">{move || auth.with_user(User::display_name)}</h1>
+                        <p id="email">
+                            {move || {
+                                auth.with_user(|user| match user.claims.metadata().organisation {
+                                    Some(organisation) => format!("{} · {organisation}", user.email),
+                                    None => user.email.clone(),
+                                })
+                            }}
+
+                        </p>
+                        <p id="user-claims">
+                            {move || {
+                                auth.with_user(|user| {
+                                    format!(
+                                        "{} · {} · {}",
+                                        user.claims.provider(),
+                                        user.claims.role,
+                                        user.claims.aal,
+                                    )
+                                })
+                            }}
+
+                        </p>
+                    </div>
+                    <button
+                        type="button"
+                        class="edit-button"
+                        on:click=move |_| {
+                            use_navigate()(&AppRoute::Settings.href(), Default::default());
+                        }
+                    >
+                        Settings
+                    </button>
                     <button type="button" on:click=logout_btn_clicked id="logout-button">
 
                         Log Out
@@ -475,7 +474,7 @@ pub fn Home(user: Signal<User>, set_user: WriteSignal<User>) -> impl IntoView {
                             value=move || {
                                 editing_company
                                     .with(|f| {
-                                        f.as_ref().and_then(|_| Some("Edit")).unwrap_or("Send")
+                                        f.as_ref().map(|_| "Edit").unwrap_or("Send")
                                     })
                             }
 
@@ -578,7 +577,7 @@ pub fn Home(user: Signal<User>, set_user: WriteSignal<User>) -> impl IntoView {
                 </form>
             </div>
 
-            <Show when=move || companies.with(|f| f.is_empty() == false)>
+            <Show when=move || companies.with(|f| !f.is_empty())>
                 <table class="styled-table">
                     <thead>
                         <tr>
@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use leptos::{html::Dialog, *};
+use leptos_router::*;
+use leptos_use::use_event_listener;
+
+use crate::core::{
+    auth_service::use_auth, config::use_config, helper::local_storage, models::LogoutScope,
+    routes::AppRoute,
+};
+
+/// How many seconds before the idle logout the warning shows up
+const IDLE_WARNING_SECS: i64 = 60;
+
+/// Local storage key of the last activity, shared so an active tab keeps the idle ones logged in
+/// too. Stored as `<session>:<unix milliseconds>`, so a new session doesn't inherit the activity
+/// of an old one.
+const LAST_ACTIVITY_KEY: &str = "last_activity";
+
+/// Activity is written at most this often, every write fires storage events in the other tabs
+const ACTIVITY_WRITE_INTERVAL_MS: i64 = 5_000;
+
+/// Idle timeout from the `idle_timeout_minutes` config, `0` disables it
+fn idle_timeout_secs() -> Option<i64> {
+    let minutes = use_config().idle_timeout_minutes;
+    (minutes > 0).then_some(i64::from(minutes) * 60)
+}
+
+fn last_activity(session: &str) -> Option<i64> {
+    let value = local_storage().get_item(LAST_ACTIVITY_KEY).ok()??;
+    let (stored_session, timestamp) = value.rsplit_once(':')?;
+    (stored_session == session).then(|| timestamp.parse().ok())?
+}
+
+fn write_activity(session: &str) {
+    local_storage()
+        .set_item(LAST_ACTIVITY_KEY, &format!("{session}:{}", Utc::now().timestamp_millis()))
+        .expect("Can't access to local storage");
+}
+
+fn record_activity(session: &str) {
+    let now = Utc::now().timestamp_millis();
+    if last_activity(session).is_none_or(|f| now - f >= ACTIVITY_WRITE_INTERVAL_MS) {
+        write_activity(session);
+    }
+}
+
+/// Logs the user out after the idle timeout without any activity, warning with a countdown first.
+/// The local data is kept so nothing unsynced gets lost.
+#[component]
+pub fn IdleTimeout() -> impl IntoView {
+    let Some(timeout_secs) = idle_timeout_secs() else {
+        return ().into_view();
+    };
+    let auth = use_auth();
+    let idle_alert = NodeRef::<Dialog>::new();
+    let seconds_left = RwSignal::new(IDLE_WARNING_SECS);
+    let warning_shown = RwSignal::new(false);
+    let session = auth
+        .with_user_untracked(|user| user.claims.session_id.clone().unwrap_or(user.uuid.clone()))
+        .unwrap_or_default();
+    let session = StoredValue::new(session);
+    let log_out_idle = {
+        let navigate = use_navigate();
+        move || {
+            auth.end_session(LogoutScope::Local);
+            navigate(
+                &AppRoute::Login.href_with(&[(
+                    "error_description",
+                    "You have been logged out due to inactivity",
+                )]),
+                Default::default(),
+            );
+        }
+    };
+
+    // The stored activity is checked before anything counts as new activity, e.g. a remembered
+    // session restored after the browser was closed for longer than the timeout ends right away
+    let now = Utc::now().timestamp_millis();
+    match session.with_value(|f| last_activity(f)) {
+        Some(last) if (now - last) / 1000 >= timeout_secs => {
+            queue_microtask(log_out_idle);
+            return ().into_view();
+        }
+        Some(_) => {}
+        None => session.with_value(|f| write_activity(f)),
+    }
+    // Moving the mouse over the warning doesn't count, it has to be dismissed
+    let on_activity = move || {
+        if !warning_shown.get_untracked() {
+            session.with_value(|f| record_activity(f));
+        }
+    };
+    let _ = use_event_listener(document(), ev::pointerdown, move |_| on_activity());
+    let _ = use_event_listener(document(), ev::mousemove, move |_| on_activity());
+    let _ = use_event_listener(document(), ev::keydown, move |_| on_activity());
+    let _ = use_event_listener(document(), ev::scroll, move |_| on_activity());
+    let _ = use_event_listener(document(), ev::touchstart, move |_| on_activity());
+
+    let check_idle = move || {
+        let now = Utc::now().timestamp_millis();
+        let idle_secs = (now - session.with_value(|f| last_activity(f)).unwrap_or(now)) / 1000;
+        let left = timeout_secs - idle_secs;
+        let Some(dialog) = idle_alert.get_untracked() else {
+            return;
+        };
+        if left <= 0 {
+            dialog.close();
+            log_out_idle();
+        } else if left <= IDLE_WARNING_SECS {
+            seconds_left.set(left);
+            if !warning_shown.get_untracked() {
+                warning_shown.set(true);
+                dialog.show_modal().unwrap_or_default();
+            }
+        } else if warning_shown.get_untracked() {
+            // Activity in another tab
+            warning_shown.set(false);
+            dialog.close();
+        }
+    };
+    let handle = set_interval_with_handle(check_idle, Duration::from_secs(1)).ok();
+    on_cleanup(move || {
+        if let Some(handle) = handle {
+            handle.clear();
+        }
+    });
+
+    let stay_logged_in = move || {
+        warning_shown.set(false);
+        session.with_value(|f| write_activity(f));
+        idle_alert.get().unwrap().close();
+    };
+
+    view! {
+        <dialog class="dialog" node_ref=idle_alert on:cancel=move |_: ev::Event| stay_logged_in()>
+            <div class="dialog-inner-box">
+                <h1>"⚠ Are you still there?"</h1>
+                <p>
+                    "You will be logged out in " {seconds_left} " seconds due to inactivity." <br/>
+                    " Your unsynced data will be kept."
+                </p>
+                <input
+                    type="button"
+                    class="primary-button"
+                    value="Stay Logged In"
+                    on:click=move |_| stay_logged_in()
+                />
+            </div>
+        </dialog>
+    }
+    .into_view()
+}
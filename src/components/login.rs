@@ -1,89 +1,74 @@
 use leptos::{html::Input, *};
 use leptos_router::*;
 
-use reqwest;
-use serde_json::{json, Value};
-
 use crate::{
     app::toast,
+    components::OAuthButtons,
     core::{
-        helper::{access_token_to_uuid_email, local_storage},
+        auth_service::use_auth,
+        helper::{app_url, countdown_label, next_path, redirect_to, wait_out_rate_limit},
         models::User,
+        routes::AppRoute,
+        session::{remember_me, set_remember_me},
     },
-    env,
 };
 
 #[component]
-pub fn LogIn(user: Signal<User>, set_user: WriteSignal<User>) -> impl IntoView {
+pub fn LogIn() -> impl IntoView {
+    let auth = use_auth();
     let email_ref = NodeRef::<Input>::new();
     let pass_ref = NodeRef::<Input>::new();
     let disable_login_btn = RwSignal::new(false);
+    let passwordless = RwSignal::new(false);
+    // Seconds until a rate-limited request may be sent again
+    let retry_in = RwSignal::new(0u64);
+    // Set by `/redirect` when GoTrue or the OAuth provider sent back an error
+    let redirect_error = use_query_map().with_untracked(|q| q.get("error_description").cloned());
+    // Passed on to the pages the user continues to from here
+    let next = use_query_map().with_untracked(|q| q.get("next").cloned().unwrap_or_default());
+    let next = StoredValue::new(next);
     let login = move |email: String, password: String| async move {
-        let client = reqwest::Client::new();
-        let body = json!({"email":email,"password":password}).to_string();
-        let res = client
-            .post(env::APP_MANUAL_LOGIN_URL)
-            .body(body)
-            .header("apikey", env::APP_API_KEY)
-            .send()
-            .await;
+        auth.authenticating();
+        let res =
+            auth.client().sign_in_with_password(&email, &password).await.and_then(User::try_from);
         match res {
-            Ok(res) => {
-                if res.status().is_success() {
-                    let user_details = res
-                        .bytes()
-                        .await
-                        .ok()
-                        .map(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
-                        .flatten();
-                    match user_details {
-                        Some(user_details) => {
-                            let access_token = user_details
-                                .get("access_token")
-                                .as_ref()
-                                .map(|access_token| access_token.as_str())
-                                .flatten()
-                                .map(str::to_string);
-                            let refresh_token = user_details
-                                .get("refresh_token")
-                                .as_ref()
-                                .map(|refresh_token| refresh_token.as_str())
-                                .flatten()
-                                .map(str::to_string);
-                            let uuid_email = access_token
-                                .as_ref()
-                                .map(|access_token| {
-                                    access_token_to_uuid_email(access_token.as_str())
-                                })
-                                .flatten();
-                            match (access_token, refresh_token, uuid_email) {
-                                (Some(access_token), Some(refresh_token), Some((uuid, email))) => {
-                                    if user.get().uuid != uuid {
-                                        local_storage()
-                                            .clear()
-                                            .expect("Can't access to local storage");
-                                    }
-                                    set_user.set(User { access_token, refresh_token, uuid, email });
-                                    use_navigate()("/csr-example-leptos-supabase/", Default::default());
-                                }
-                                _ => {
-                                    toast(String::from("User token is not valid"));
-                                }
-                            }
-                        }
-                        None => {
-                            toast(String::from("User token is not valid"));
-                        }
-                    }
-                } else {
-                    toast(format!(
-                        "Login Failed. Response message: {}",
-                        res.status().canonical_reason().unwrap_or("Nothing")
-                    ));
-                }
+            Ok(new_user) => {
+                auth.sign_in(new_user);
+                use_navigate()(&next_path(), Default::default());
+            }
+            Err(err) => {
+                auth.sign_in_failed();
+                wait_out_rate_limit(&err, retry_in);
+                toast(format!("Login Failed. {err}"));
+            }
+        }
+    };
+    let forgot_password = move |email: String| async move {
+        match auth.client().recover(&email, &redirect_to(&app_url(AppRoute::ResetPassword))).await {
+            Ok(_) => {
+                use_navigate()(&AppRoute::RecoverConfirmation.href(), Default::default());
+            }
+            Err(err) => {
+                wait_out_rate_limit(&err, retry_in);
+                toast(format!("Password Recovery Failed. {err}"));
+            }
+        }
+    };
+    let send_login_code = move |email: String| async move {
+        match auth
+            .client()
+            .sign_in_with_otp(&email, &redirect_to(&app_url(AppRoute::Redirect)))
+            .await
+        {
+            Ok(_) => {
+                use_navigate()(
+                    &AppRoute::Verify.href_with(&[("email", &email), ("next", &next.get_value())]),
+                    Default::default(),
+                );
             }
             Err(err) => {
-                toast(format!("Login Failed. Error: {}", err.to_string()));
+                wait_out_rate_limit(&err, retry_in);
+                toast(format!("Sending Login Code Failed. {err}"));
             }
         }
     };
@@ -95,44 +80,105 @@ pub fn LogIn(user: Signal<User>, set_user: WriteSignal<User>) -> impl IntoView {
                     event.prevent_default();
                     spawn_local(async move {
                         disable_login_btn.set(true);
-                        login(email_ref.get().unwrap().value(), pass_ref.get().unwrap().value())
-                            .await;
+                        if passwordless.get_untracked() {
+                            send_login_code(email_ref.get().unwrap().value()).await;
+                        } else {
+                            login(email_ref.get().unwrap().value(), pass_ref.get().unwrap().value())
+                                .await;
+                        }
                         disable_login_btn.set(false);
                     });
                 }
             >
 
                 <h1>Login</h1>
+                {redirect_error.map(|err| view! { <p class="form-error">{err}</p> })}
                 <label for="email">
                     Email: <input node_ref=email_ref type="email" name="email" id="email" required/>
                 </label>
-                <label for="pass">
-                    Password:
-                    <input node_ref=pass_ref type="password" name="pass" id="pass" required/>
+                <Show when=move || !passwordless.get()>
+                    <label for="pass">
+                        Password:
+                        <input node_ref=pass_ref type="password" name="pass" id="pass" required/>
+                    </label>
+                </Show>
+                <label for="remember-me" class="remember-me">
+                    <input
+                        type="checkbox"
+                        name="remember-me"
+                        id="remember-me"
+                        checked=remember_me()
+                        on:change=move |event| set_remember_me(event_target_checked(&event))
+                    />
+                    "Remember me"
                 </label>
+                <div class="form-links">
+                    <a
+                        href="#"
+                        on:click=move |event| {
+                            event.prevent_default();
+                            passwordless.update(|f| *f = !*f);
+                        }
+                    >
+
+                        {move || {
+                            if passwordless.get() {
+                                "Sign in with password"
+                            } else {
+                                "Email me a login code"
+                            }
+                        }}
+
+                    </a>
+                    <Show when=move || !passwordless.get()>
+                        <a
+                            href="#"
+                            on:click=move |event| {
+                                event.prevent_default();
+                                let email_input = email_ref.get().unwrap();
+                                if retry_in.get_untracked() > 0 {
+                                    return;
+                                }
+                                if !email_input.check_validity() {
+                                    toast(String::from("Please enter your email first"));
+                                    return;
+                                }
+                                spawn_local(async move {
+                                    disable_login_btn.set(true);
+                                    forgot_password(email_input.value()).await;
+                                    disable_login_btn.set(false);
+                                });
+                            }
+                        >
+
+                            "Forgot password?"
+                        </a>
+                    </Show>
+                </div>
                 <input
                     type="submit"
                     class="primary-button"
-                    value="LogIn"
-                    disabled=disable_login_btn
+                    value=move || {
+                        let label = if passwordless.get() { "Send Login Code" } else { "LogIn" };
+                        countdown_label(label, retry_in.get())
+                    }
+                    disabled=move || { disable_login_btn.get() || retry_in.get() > 0 }
                 />
                 <input
                     type="button"
                     class="secondary-button"
                     value="SignUp"
                     on:click=move |_| {
-                        use_navigate()("/csr-example-leptos-supabase/signup", Default::default());
+                        use_navigate()(
+                            &AppRoute::SignUp.href_with(&[("next", &next.get_value())]),
+                            Default::default(),
+                        );
                     }
                 />
 
-                <div class="or">OR</div>
-
-                <a type="button" class="login-with-google-btn" href=env::APP_GOOGLE_LOGIN_URL>
-                    "Continue with Google"
-                </a>
+                <OAuthButtons/>
             </form>
 
         </div>
     }
 }
-
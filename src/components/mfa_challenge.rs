@@ -0,0 +1,131 @@
+use leptos::{html::Input, *};
+use leptos_router::*;
+
+use crate::{
+    app::toast,
+    core::{
+        auth_service::use_auth,
+        helper::next_path,
+        models::{LogoutScope, User},
+        routes::AppRoute,
+    },
+};
+
+/// Second login step for `aal1` sessions, verifying a code of the user's TOTP factor
+#[component]
+pub fn MfaChallenge() -> impl IntoView {
+    let auth = use_auth();
+    let code_ref = NodeRef::<Input>::new();
+    let disable_verify_btn = RwSignal::new(true);
+    // (factor id, challenge id) of the pending challenge
+    let challenge = RwSignal::new(Option::<(String, String)>::None);
+
+    let start_challenge = move || async move {
+        disable_verify_btn.set(true);
+        let access_token = match auth.fresh_access_token().await {
+            Ok(access_token) => access_token,
+            Err(err) => {
+                toast(format!("MFA Challenge Failed. {err}"));
+                return;
+            }
+        };
+        let auth_client = auth.client();
+        let factor = match auth_client.get_user(&access_token).await {
+            Ok(auth_user) => auth_user.verified_totp_factors().into_iter().next(),
+            Err(err) => {
+                toast(format!("MFA Challenge Failed. {err}"));
+                return;
+            }
+        };
+        let Some(factor) = factor else {
+            // Users without a factor have to enroll one before using the app, keeping the `next`
+            let search = window().location().search().unwrap_or_default();
+            use_navigate()(&format!("{}{search}", AppRoute::MfaEnroll.href()), Default::default());
+            return;
+        };
+        match auth_client.challenge_factor(&access_token, &factor.id).await {
+            Ok(new_challenge) => {
+                challenge.set(Some((factor.id, new_challenge.id)));
+                disable_verify_btn.set(false);
+            }
+            Err(err) => {
+                toast(format!("MFA Challenge Failed. {err}"));
+            }
+        }
+    };
+    let verify = move |code: String| async move {
+        let Some((factor_id, challenge_id)) = challenge.get_untracked() else {
+            return;
+        };
+        let res = match auth.fresh_access_token().await {
+            Ok(access_token) => auth
+                .client()
+                .verify_factor(&access_token, &factor_id, &challenge_id, &code)
+                .await
+                .and_then(User::try_from),
+            Err(err) => Err(err),
+        };
+        match res {
+            Ok(new_user) => {
+                auth.sign_in(new_user);
+                use_navigate()(&next_path(), Default::default());
+            }
+            Err(err) => {
+                toast(format!("Verification Failed. {err}"));
+                // A challenge can only be verified once
+                start_challenge().await;
+            }
+        }
+    };
+
+    spawn_local(start_challenge());
+
+    view! {
+        <div id="login-container">
+            <form
+                id="login-signup-form"
+                on:submit=move |event| {
+                    event.prevent_default();
+                    spawn_local(async move {
+                        disable_verify_btn.set(true);
+                        verify(code_ref.get().unwrap().value()).await;
+                    });
+                }
+            >
+
+                <h1>Two-Factor Authentication</h1>
+                <p>"Enter the 6-digit code from your authenticator app."</p>
+                <label for="code">
+                    Code:
+                    <input
+                        node_ref=code_ref
+                        type="text"
+                        name="code"
+                        id="code"
+                        inputmode="numeric"
+                        autocomplete="one-time-code"
+                        pattern="[0-9]{6}"
+                        maxlength="6"
+                        required
+                    />
+                </label>
+                <input
+                    type="submit"
+                    class="primary-button"
+                    value="Verify"
+                    disabled=disable_verify_btn
+                />
+                <input
+                    type="button"
+                    class="secondary-button"
+                    value="Log Out"
+                    on:click=move |_| {
+                        auth.log_out(LogoutScope::Local);
+                        use_navigate()(&AppRoute::Login.href(), Default::default());
+                    }
+                />
+
+            </form>
+        </div>
+    }
+}
@@ -0,0 +1,128 @@
+use leptos::{html::Input, *};
+use leptos_router::*;
+
+use crate::{
+    app::toast,
+    core::{
+        auth_service::use_auth,
+        helper::next_path,
+        models::{LogoutScope, TotpEnrollment, User},
+        routes::AppRoute,
+    },
+};
+
+/// Enrolls a TOTP factor: shows the QR code/secret for the authenticator app and verifies its
+/// first code, which also upgrades the session to `aal2`
+#[component]
+pub fn MfaEnroll() -> impl IntoView {
+    let auth = use_auth();
+    let code_ref = NodeRef::<Input>::new();
+    let disable_verify_btn = RwSignal::new(false);
+    let enrollment = RwSignal::new(Option::<TotpEnrollment>::None);
+
+    spawn_local(async move {
+        let res = match auth.fresh_access_token().await {
+            Ok(access_token) => auth.client().enroll_totp(&access_token).await,
+            Err(err) => Err(err),
+        };
+        match res {
+            Ok(new_enrollment) => enrollment.set(Some(new_enrollment)),
+            Err(err) => toast(format!("MFA Enrollment Failed. {err}")),
+        }
+    });
+
+    let verify = move |code: String| async move {
+        let Some(factor_id) = enrollment.with_untracked(|f| f.as_ref().map(|f| f.id.clone()))
+        else {
+            return;
+        };
+        let auth_client = auth.client();
+        let res = match auth.fresh_access_token().await {
+            Ok(access_token) => match auth_client.challenge_factor(&access_token, &factor_id).await
+            {
+                Ok(challenge) => auth_client
+                    .verify_factor(&access_token, &factor_id, &challenge.id, &code)
+                    .await
+                    .and_then(User::try_from),
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        };
+        match res {
+            Ok(new_user) => {
+                auth.sign_in(new_user);
+                use_navigate()(&next_path(), Default::default());
+            }
+            Err(err) => {
+                toast(format!("Verification Failed. {err}"));
+            }
+        }
+    };
+
+    view! {
+        <div id="login-container">
+            <form
+                id="login-signup-form"
+                on:submit=move |event| {
+                    event.prevent_default();
+                    spawn_local(async move {
+                        disable_verify_btn.set(true);
+                        verify(code_ref.get().unwrap().value()).await;
+                        disable_verify_btn.set(false);
+                    });
+                }
+            >
+
+                <h1>Set Up Two-Factor Authentication</h1>
+                <p>
+                    "Scan the QR code with your authenticator app, or enter the secret manually."
+                </p>
+                {move || {
+                    enrollment
+                        .get()
+                        .map(|enrollment| {
+                            view! {
+                                <img
+                                    class="mfa-qr-code"
+                                    src=enrollment.totp.qr_code
+                                    alt="TOTP QR code"
+                                />
+                                <code class="mfa-secret">{enrollment.totp.secret}</code>
+                            }
+                        })
+                }}
+
+                <label for="code">
+                    Code:
+                    <input
+                        node_ref=code_ref
+                        type="text"
+                        name="code"
+                        id="code"
+                        inputmode="numeric"
+                        autocomplete="one-time-code"
+                        pattern="[0-9]{6}"
+                        maxlength="6"
+                        required
+                    />
+                </label>
+                <input
+                    type="submit"
+                    class="primary-button"
+                    value="Verify"
+                    disabled=move || disable_verify_btn.get() || enrollment.with(Option::is_none)
+                />
+                <input
+                    type="button"
+                    class="secondary-button"
+                    value="Log Out"
+                    on:click=move |_| {
+                        auth.log_out(LogoutScope::Local);
+                        use_navigate()(&AppRoute::Login.href(), Default::default());
+                    }
+                />
+
+            </form>
+        </div>
+    }
+}
@@ -1,8 +1,32 @@
+mod auth_redirect;
 mod home;
+mod idle_timeout;
 mod login;
+mod mfa_challenge;
+mod mfa_enroll;
+mod oauth_buttons;
+mod password_input;
+mod profile_badge;
+mod require_auth;
+mod reset_password;
+mod settings;
 mod signup;
+mod signup_confirmation;
+mod verify_otp;
 
+pub use auth_redirect::AuthRedirect;
 pub use home::Home;
+pub use idle_timeout::IdleTimeout;
 pub use login::LogIn;
+pub use mfa_challenge::MfaChallenge;
+pub use mfa_enroll::MfaEnroll;
+pub use oauth_buttons::OAuthButtons;
+pub use password_input::{NewPassword, PasswordInput};
+pub use profile_badge::ProfileBadge;
+pub use require_auth::RequireAuth;
+pub use reset_password::ResetPassword;
+pub use settings::Settings;
 pub use signup::SignUp;
+pub use signup_confirmation::SignUpConfirmation;
+pub use verify_otp::VerifyOtp;
 
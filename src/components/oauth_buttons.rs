@@ -0,0 +1,73 @@
+use leptos::*;
+
+use crate::{
+    app::toast,
+    core::{
+        auth_service::use_auth,
+        config::use_config,
+        helper::redirect_to,
+        pkce,
+        providers::{enabled_providers, OAuthProvider},
+    },
+};
+
+/// A "Continue with ..." button per enabled OAuth provider, all calling back to `/redirect`
+#[component]
+pub fn OAuthButtons() -> impl IntoView {
+    let config = use_config();
+    let auth_client = StoredValue::new(use_auth().client());
+    let providers = enabled_providers(&config);
+    let redirect_url = StoredValue::new(config.redirect_url);
+
+    // Read when leaving, as "Remember me" can change after the buttons are rendered
+    let authorize_url = move |provider: OAuthProvider, code_challenge: Option<&str>| {
+        auth_client.with_value(|auth_client| {
+            let redirect_to = redirect_url.with_value(|f| redirect_to(f));
+            auth_client.authorize_url(provider, &redirect_to, code_challenge)
+        })
+    };
+    // The code challenge is created right before leaving, so each attempt gets a fresh verifier
+    let sign_in_with_pkce = move |provider: OAuthProvider| async move {
+        match pkce::new_code_challenge().await {
+            Ok(code_challenge) => {
+                let url = authorize_url(provider, Some(&code_challenge));
+                window().location().set_href(&url).expect("Can't access to the location");
+            }
+            Err(err) => {
+                toast(format!("Login Failed. Error: {err:?}"));
+            }
+        }
+    };
+
+    (!providers.is_empty()).then(|| {
+        view! {
+            <div class="or">OR</div>
+            {providers
+                .into_iter()
+                .map(|provider| {
+                    view! {
+                        <a
+                            type="button"
+                            class=format!("oauth-btn {}", provider.id())
+                            href=authorize_url(provider, None)
+                            on:click=move |event| {
+                                event.prevent_default();
+                                if pkce::ENABLED {
+                                    spawn_local(sign_in_with_pkce(provider));
+                                } else {
+                                    window()
+                                        .location()
+                                        .set_href(&authorize_url(provider, None))
+                                        .expect("Can't access to the location");
+                                }
+                            }
+                        >
+
+                            {format!("Continue with {}", provider.name())}
+                        </a>
+                    }
+                })
+                .collect_view()}
+        }
+    })
+}
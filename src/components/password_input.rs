@@ -0,0 +1,103 @@
+use leptos::{html::Input, *};
+
+use crate::core::{
+    config::use_config,
+    password::{password_policy, PasswordIssue, PasswordPolicy, PasswordStrength},
+};
+
+/// A new password being typed into a `PasswordInput`, read by the form when it's submitted
+#[derive(Debug, Clone, Copy)]
+pub struct NewPassword {
+    value: RwSignal<String>,
+    /// Rules GoTrue rejected the password for, until it's edited again
+    rejected: RwSignal<Vec<PasswordIssue>>,
+    policy: PasswordPolicy,
+}
+
+impl Default for NewPassword {
+    fn default() -> Self {
+        NewPassword {
+            value: RwSignal::new(String::new()),
+            rejected: RwSignal::new(Vec::new()),
+            policy: password_policy(&use_config()),
+        }
+    }
+}
+
+impl NewPassword {
+    pub fn get_untracked(&self) -> String {
+        self.value.get_untracked()
+    }
+
+    /// Shows the `weak_password` reasons of GoTrue with the other unmet rules
+    pub fn reject(&self, reasons: &[String]) {
+        self.rejected.set(self.policy.issues_from_reasons(reasons));
+    }
+}
+
+/// Password field checked against the password policy while it's typed, with a strength meter
+/// and the unmet rules. The form can't be submitted until they're met.
+#[component]
+pub fn PasswordInput(
+    password: NewPassword,
+    /// For the `no_email` rule
+    #[prop(into)]
+    email: Signal<String>,
+    id: &'static str,
+    label: &'static str,
+) -> impl IntoView {
+    let NewPassword { value, rejected, policy } = password;
+    let issues = Memo::new(move |_| {
+        let mut issues = policy.issues(&value.get(), &email.get());
+        issues.extend(rejected.get());
+        issues
+    });
+    let input_ref = NodeRef::<Input>::new();
+    Effect::new(move |_| {
+        let message =
+            if issues.with(Vec::is_empty) { "" } else { "Password doesn't meet the requirements" };
+        if let Some(input) = input_ref.get() {
+            input.set_custom_validity(message);
+        }
+    });
+
+    view! {
+        <label for=id>
+            {label}
+            <input
+                node_ref=input_ref
+                type="password"
+                name=id
+                id=id
+                required
+                minlength=policy.min_length
+                on:input=move |event| {
+                    value.set(event_target_value(&event));
+                    rejected.set(Vec::new());
+                }
+            />
+
+        </label>
+        <Show when=move || value.with(|f| !f.is_empty())>
+            {move || {
+                let strength = PasswordStrength::of(&value.get());
+                view! {
+                    <div class=format!("password-strength {}", strength.as_str())>
+                        <div class="password-strength-bar"></div>
+                        <span>{format!("Strength: {}", strength.as_str())}</span>
+                    </div>
+                }
+            }}
+            <ul class="password-issues">
+                {move || {
+                    issues
+                        .get()
+                        .into_iter()
+                        .map(|issue| view! { <li>{issue.to_string()}</li> })
+                        .collect_view()
+                }}
+
+            </ul>
+        </Show>
+    }
+}
@@ -0,0 +1,29 @@
+use leptos::*;
+
+use crate::core::config::use_config;
+
+/// Names the profile and Supabase project of non-production builds, so testers know which data
+/// they're working with
+#[component]
+pub fn ProfileBadge() -> impl IntoView {
+    let config = use_config();
+    if config.is_production() {
+        return ().into_view();
+    }
+    let project = config
+        .supabase_url
+        .split("://")
+        .last()
+        .unwrap_or_default()
+        .trim_end_matches('/')
+        .to_string();
+
+    view! {
+        <div id="profile-badge" title=config.supabase_url>
+            {config.profile.to_uppercase()}
+            " · "
+            {project}
+        </div>
+    }
+    .into_view()
+}
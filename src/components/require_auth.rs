@@ -0,0 +1,57 @@
+use leptos::*;
+use leptos_router::*;
+
+use crate::core::{
+    auth_service::{use_auth, AuthState, SESSION_EXPIRED},
+    models::Aal,
+    routes::AppRoute,
+};
+
+/// Where the auth state sends the user, the children are only rendered again when it changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Login,
+    Expired,
+    MfaChallenge,
+    Granted,
+}
+
+/// Renders its children for logged in users only, anyone else is sent to the login page which
+/// returns to this page afterwards. Our security policy requires MFA for the whole app, so `aal1`
+/// sessions are sent to the MFA challenge first unless `allow_aal1` is set. Follows the auth
+/// state, e.g. logging out in another tab or the session expiring redirects right away.
+#[component]
+pub fn RequireAuth(#[prop(optional)] allow_aal1: bool, children: ChildrenFn) -> impl IntoView {
+    let location = use_location();
+    let auth = use_auth();
+    let access = Memo::new(move |_| match auth.state().get() {
+        AuthState::Anonymous | AuthState::Authenticating => Access::Login,
+        AuthState::Expired => Access::Expired,
+        AuthState::MfaRequired(_) if !allow_aal1 => Access::MfaChallenge,
+        AuthState::Refreshing(user) if !allow_aal1 && user.claims.aal != Aal::Aal2 => {
+            Access::MfaChallenge
+        }
+        _ => Access::Granted,
+    });
+
+    move || {
+        let next =
+            format!("{}{}", location.pathname.get_untracked(), location.search.get_untracked());
+        match access.get() {
+            Access::Login => {
+                view! { <Redirect path=AppRoute::Login.href_with(&[("next", &next)])/> }
+            }
+            Access::Expired => {
+                let path = AppRoute::Login
+                    .href_with(&[("next", &next), ("error_description", SESSION_EXPIRED)]);
+                view! { <Redirect path=path/> }
+            }
+            Access::MfaChallenge => {
+                view! {
+                    <Redirect path=AppRoute::MfaChallenge.href_with(&[("next", &next)])/>
+                }
+            }
+            Access::Granted => children().into_view(),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use leptos::{html::Input, *};
+use leptos_router::*;
+
+use crate::{
+    app::toast,
+    components::{NewPassword, PasswordInput},
+    core::{
+        auth_service::use_auth,
+        helper::{restore_remember_me, url_hash_to_user, url_params},
+        models::{AuthError, RedirectError, UserAttributes},
+        routes::AppRoute,
+    },
+};
+
+#[component]
+pub fn ResetPassword() -> impl IntoView {
+    let auth = use_auth();
+    let new_password = NewPassword::default();
+    let confirm_pass_ref = NodeRef::<Input>::new();
+    let disable_reset_btn = RwSignal::new(false);
+
+    // The recovery link logs the user in with a session delivered in the URL hash
+    let recovery_user = {
+        restore_remember_me(&url_params(&use_location().search.get_untracked()));
+        let url_hash = use_location().hash.get_untracked();
+        let is_recovery = url_params(&url_hash).get("type").is_some_and(|f| f == "recovery");
+        url_hash_to_user(url_hash).and_then(|new_user| {
+            if is_recovery {
+                Ok(new_user)
+            } else {
+                Err(RedirectError::MissingSession)
+            }
+        })
+    };
+    let recovery_user = StoredValue::new(recovery_user);
+    let email = recovery_user.with_value(|f| f.as_ref().ok().map(|f| f.email.clone()));
+    let email = Signal::derive(move || email.clone().unwrap_or_default());
+
+    let reset_password = move |password: String| async move {
+        let Ok(new_user) = recovery_user.get_value() else {
+            return;
+        };
+        let attributes = UserAttributes { password: Some(password), ..Default::default() };
+        match auth.client().update_user(&new_user.access_token, &attributes, None).await {
+            Ok(_) => {
+                auth.sign_in(new_user);
+                use_navigate()(&AppRoute::Home.href(), Default::default());
+            }
+            Err(AuthError::WeakPassword { reasons, .. }) => {
+                new_password.reject(&reasons);
+            }
+            Err(err) => {
+                toast(format!("Password Reset Failed. {err}"));
+            }
+        }
+    };
+
+    view! {
+        <div id="login-container">
+            <Show
+                when=move || recovery_user.with_value(Result::is_ok)
+                fallback=move || {
+                    view! {
+                        <div id="login-signup-form">
+                            <h1>Reset Password</h1>
+                            <p>"The password reset link is invalid or has expired."</p>
+                            <p class="form-error">
+                                {recovery_user
+                                    .with_value(|f| f.as_ref().err().map(ToString::to_string))}
+                            </p>
+                            <input
+                                type="button"
+                                class="secondary-button"
+                                value="LogIn"
+                                on:click=move |_| {
+                                    use_navigate()(&AppRoute::Login.href(), Default::default());
+                                }
+                            />
+                        </div>
+                    }
+                }
+            >
+
+                <form
+                    id="login-signup-form"
+                    on:submit=move |event| {
+                        event.prevent_default();
+                        let password = new_password.get_untracked();
+                        if password != confirm_pass_ref.get().unwrap().value() {
+                            toast(String::from("Passwords don't match"));
+                            return;
+                        }
+                        spawn_local(async move {
+                            disable_reset_btn.set(true);
+                            reset_password(password).await;
+                            disable_reset_btn.set(false);
+                        });
+                    }
+                >
+
+                    <h1>Reset Password</h1>
+                    <PasswordInput
+                        password=new_password
+                        email=email
+                        id="pass"
+                        label="New Password:"
+                    />
+                    <label for="confirm-pass">
+                        Confirm Password:
+                        <input
+                            node_ref=confirm_pass_ref
+                            type="password"
+                            name="confirm-pass"
+                            id="confirm-pass"
+                            required
+                        />
+                    </label>
+                    <input
+                        type="submit"
+                        class="primary-button"
+                        value="Set Password"
+                        disabled=disable_reset_btn
+                    />
+                </form>
+            </Show>
+        </div>
+    }
+}
@@ -0,0 +1,267 @@
+use leptos::{
+    html::{Dialog, Input},
+    *,
+};
+use leptos_router::*;
+
+use crate::{
+    app::toast,
+    components::{NewPassword, PasswordInput},
+    core::{
+        auth_service::use_auth,
+        config::use_config,
+        helper::{app_url, postgrest_client, redirect_to},
+        models::{AuthError, LogoutScope, UserAttributes},
+        routes::AppRoute,
+    },
+};
+
+/// Account settings of the logged in user: email, password, sessions and account deletion
+#[component]
+pub fn Settings() -> impl IntoView {
+    let auth = use_auth();
+    let postgrest_client = StoredValue::new(postgrest_client(&use_config()));
+    let new_email_ref = NodeRef::<Input>::new();
+    let new_password = NewPassword::default();
+    let confirm_pass_ref = NodeRef::<Input>::new();
+    let delete_account_alert = NodeRef::<Dialog>::new();
+    let disable_buttons = RwSignal::new(false);
+    // Set by `/redirect` after following an email change confirmation link
+    let message = RwSignal::new(use_query_map().with_untracked(|q| q.get("message").cloned()));
+    let email =
+        Signal::derive(move || auth.with_user(|user| user.email.clone()).unwrap_or_default());
+
+    let update_user = move |attributes: UserAttributes, redirect_to: Option<String>| async move {
+        let access_token = auth.fresh_access_token().await?;
+        auth.client().update_user(&access_token, &attributes, redirect_to.as_deref()).await
+    };
+    let change_email = move |email: String| async move {
+        let attributes = UserAttributes { email: Some(email.clone()), ..Default::default() };
+        match update_user(attributes, Some(redirect_to(&app_url(AppRoute::Redirect)))).await {
+            Ok(_) => message.set(Some(format!(
+                "Please confirm the change from the links sent to {} and {email}",
+                auth.with_user_untracked(|user| user.email.clone()).unwrap_or_default()
+            ))),
+            Err(err) => toast(format!("Changing Email Failed. {err}")),
+        }
+    };
+    let change_password = move |password: String| async move {
+        let attributes = UserAttributes { password: Some(password), ..Default::default() };
+        match update_user(attributes, None).await {
+            Ok(_) => message.set(Some(String::from("Your password has been changed"))),
+            Err(AuthError::WeakPassword { reasons, .. }) => {
+                new_password.reject(&reasons);
+            }
+            Err(err) => toast(format!("Changing Password Failed. {err}")),
+        }
+    };
+    let sign_out_other_devices = move || async move {
+        let res = match auth.fresh_access_token().await {
+            Ok(access_token) => auth.client().logout(&access_token, LogoutScope::Others).await,
+            Err(err) => Err(err),
+        };
+        match res {
+            Ok(_) => message.set(Some(String::from("Signed out of all other devices"))),
+            Err(AuthError::Network(_)) => {
+                toast(String::from("You seem to be offline, please try again when you're online"))
+            }
+            Err(err) => toast(format!("Signing Out Other Devices Failed. {err}")),
+        }
+    };
+    let delete_account = move || async move {
+        let access_token = match auth.fresh_access_token().await {
+            Ok(access_token) => access_token,
+            Err(err) => {
+                toast(format!("Deleting Account Failed. {err}"));
+                return;
+            }
+        };
+        // The `delete_user` function removes the companies of the user before the user itself
+        let response = postgrest_client
+            .get_value()
+            .rpc("delete_user", "{}")
+            .auth(access_token)
+            .execute()
+            .await;
+        match response {
+            Ok(response) if response.status().is_success() => {
+                auth.discard_session();
+                use_navigate()(&AppRoute::Login.href(), Default::default());
+            }
+            Ok(response) => toast(format!(
+                "Deleting Account Failed. Response message: {}",
+                response.status().canonical_reason().unwrap_or("Nothing")
+            )),
+            Err(err) => toast(format!("Deleting Account Failed. Error: {err}")),
+        }
+    };
+
+    view! {
+        <div id="main">
+            <div id="main-column">
+                <div id="user-info">
+                    <div id="user-details">
+                        <h1 id="email">{move || auth.with_user(|user| user.email.clone())}</h1>
+                    </div>
+                    <button
+                        type="button"
+                        class="edit-button"
+                        on:click=move |_| {
+                            use_navigate()(&AppRoute::Home.href(), Default::default());
+                        }
+                    >
+                        Back
+                    </button>
+                </div>
+                {move || message.get().map(|message| view! { <p class="form-info">{message}</p> })}
+
+                <form
+                    class="settings-form"
+                    on:submit=move |event| {
+                        event.prevent_default();
+                        spawn_local(async move {
+                            disable_buttons.set(true);
+                            change_email(new_email_ref.get().unwrap().value()).await;
+                            disable_buttons.set(false);
+                        });
+                    }
+                >
+
+                    <h2>Change Email</h2>
+                    <label for="new-email">
+                        New Email:
+                        <input
+                            node_ref=new_email_ref
+                            type="email"
+                            name="new-email"
+                            id="new-email"
+                            required
+                        />
+                    </label>
+                    <input
+                        type="submit"
+                        class="primary-button"
+                        value="Change Email"
+                        disabled=disable_buttons
+                    />
+                </form>
+
+                <form
+                    class="settings-form"
+                    on:submit=move |event| {
+                        event.prevent_default();
+                        let password = new_password.get_untracked();
+                        if password != confirm_pass_ref.get().unwrap().value() {
+                            toast(String::from("Passwords don't match"));
+                            return;
+                        }
+                        spawn_local(async move {
+                            disable_buttons.set(true);
+                            change_password(password).await;
+                            disable_buttons.set(false);
+                        });
+                    }
+                >
+
+                    <h2>Change Password</h2>
+                    <PasswordInput
+                        password=new_password
+                        email=email
+                        id="new-pass"
+                        label="New Password:"
+                    />
+                    <label for="confirm-pass">
+                        Confirm Password:
+                        <input
+                            node_ref=confirm_pass_ref
+                            type="password"
+                            name="confirm-pass"
+                            id="confirm-pass"
+                            required
+                        />
+                    </label>
+                    <input
+                        type="submit"
+                        class="primary-button"
+                        value="Change Password"
+                        disabled=disable_buttons
+                    />
+                </form>
+
+                <div class="settings-form">
+                    <h2>Sessions</h2>
+                    <input
+                        type="button"
+                        class="secondary-button"
+                        value="Sign out of all other devices"
+                        disabled=disable_buttons
+                        on:click=move |_| {
+                            spawn_local(async move {
+                                disable_buttons.set(true);
+                                sign_out_other_devices().await;
+                                disable_buttons.set(false);
+                            });
+                        }
+                    />
+
+                    <input
+                        type="button"
+                        class="secondary-button"
+                        value="Sign out everywhere"
+                        disabled=disable_buttons
+                        on:click=move |_| {
+                            auth.log_out(LogoutScope::Global);
+                            use_navigate()(&AppRoute::Login.href(), Default::default());
+                        }
+                    />
+
+                </div>
+
+                <div class="settings-form">
+                    <h2>Delete Account</h2>
+                    <p>"Your account and all of your companies will be deleted permanently."</p>
+                    <input
+                        type="button"
+                        class="error-button"
+                        value="Delete Account"
+                        disabled=disable_buttons
+                        on:click=move |_| {
+                            delete_account_alert.get().unwrap().show_modal().unwrap_or_default();
+                        }
+                    />
+
+                </div>
+
+                <dialog class="dialog" node_ref=delete_account_alert>
+                    <div class="dialog-inner-box">
+                        <h1>"⚠ Delete your account?"</h1>
+                        <p>"This can't be undone."</p>
+                        <input
+                            type="button"
+                            class="error-button"
+                            value="Delete Account"
+                            disabled=disable_buttons
+                            on:click=move |_| {
+                                spawn_local(async move {
+                                    disable_buttons.set(true);
+                                    delete_account().await;
+                                    disable_buttons.set(false);
+                                });
+                            }
+                        />
+
+                        <input
+                            type="button"
+                            class="secondary-button"
+                            value="Cancel"
+                            on:click=move |_| {
+                                delete_account_alert.get().unwrap().close();
+                            }
+                        />
+
+                    </div>
+                </dialog>
+            </div>
+        </div>
+    }
+}
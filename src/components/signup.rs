@@ -1,39 +1,61 @@
-use leptos::{html::Input, *};
+use leptos::*;
 use leptos_router::*;
-use serde_json::json;
 
-use crate::{app::toast, env};
+use crate::{
+    app::toast,
+    components::{NewPassword, OAuthButtons, PasswordInput},
+    core::{
+        auth_service::use_auth,
+        helper::{countdown_label, next_path, wait_out_rate_limit},
+        models::{AuthError, SignUpResponse, User, UserMetadata},
+        routes::AppRoute,
+    },
+};
 #[component]
 pub fn SignUp() -> impl IntoView {
-    let email_ref = NodeRef::<Input>::new();
-    let pass_ref = NodeRef::<Input>::new();
+    let auth = use_auth();
+    let email = RwSignal::new(String::new());
+    let new_password = NewPassword::default();
+    let full_name = RwSignal::new(String::new());
+    let organisation = RwSignal::new(String::new());
     let disable_login_btn = RwSignal::new(false);
+    // Seconds until a rate-limited request may be sent again
+    let retry_in = RwSignal::new(0u64);
+    // Passed on from the login page, and back to it
+    let next = use_query_map().with_untracked(|q| q.get("next").cloned().unwrap_or_default());
+    let next = StoredValue::new(next);
 
     let signup = move |email: String, password: String| async move {
-        let client = reqwest::Client::new();
-        let body = json!({"email":email,"password":password}).to_string();
-        let res = client
-            .post(env::APP_SIGNUP_URL)
-            .body(body)
-            .header("apikey", env::APP_API_KEY)
-            .send()
-            .await;
-        match res {
-            Ok(res) => {
-                if res.status().is_success() {
-                    use_navigate()(
-                        "/csr-example-leptos-supabase/signup/confirmation",
-                        Default::default(),
-                    );
-                } else {
-                    toast(format!(
-                        "Login Failed. Response message: {}",
-                        res.status().canonical_reason().unwrap_or("Nothing")
-                    ));
+        let metadata = UserMetadata {
+            full_name: Some(full_name.get_untracked().trim().to_string()),
+            organisation: Some(organisation.get_untracked().trim().to_string())
+                .filter(|f| !f.is_empty()),
+            ..Default::default()
+        };
+        match auth.client().sign_up(&email, &password, &metadata).await {
+            // Projects with auto-confirm enabled return the session right away
+            Ok(SignUpResponse::Session(session)) => match User::try_from(session) {
+                Ok(new_user) => {
+                    auth.sign_in(new_user);
+                    use_navigate()(&next_path(), Default::default());
                 }
+                Err(err) => toast(format!("Signup Failed. {err}")),
+            },
+            Ok(SignUpResponse::User(auth_user)) if auth_user.is_already_registered() => {
+                toast(String::from("An account with this email already exists, please log in"));
+            }
+            Ok(SignUpResponse::User(_)) => {
+                use_navigate()(
+                    &AppRoute::SignUpConfirmation.href_with(&[("email", &email)]),
+                    Default::default(),
+                );
+            }
+            Err(AuthError::WeakPassword { reasons, .. }) => {
+                new_password.reject(&reasons);
             }
             Err(err) => {
-                toast(format!("Login Failed. Error: {}", err.to_string()));
+                wait_out_rate_limit(&err, retry_in);
+                toast(format!("Signup Failed. {err}"));
             }
         }
     };
@@ -47,8 +69,7 @@ pub fn SignUp() -> impl IntoView {
                     event.prevent_default();
                     spawn_local(async move {
                         disable_login_btn.set(true);
-                        signup(email_ref.get().unwrap().value(), pass_ref.get().unwrap().value())
-                            .await;
+                        signup(email.get_untracked(), new_password.get_untracked()).await;
                         disable_login_btn.set(false);
                     })
                 }
@@ -56,25 +77,43 @@ pub fn SignUp() -> impl IntoView {
 
                 <h1>SignUp</h1>
 
-                <label for="email">
-                    Email: <input node_ref=email_ref type="email" name="email" id="email" required/>
+                <label for="full-name">
+                    Full Name:
+                    <input
+                        type="text"
+                        name="full-name"
+                        id="full-name"
+                        autocomplete="name"
+                        required
+                        on:input=move |event| full_name.set(event_target_value(&event))
+                    />
                 </label>
-                <label for="pass">
-                    Password:
+                <label for="organisation">
+                    Organisation (optional):
                     <input
-                        node_ref=pass_ref
-                        type="password"
-                        name="pass"
-                        id="pass"
+                        type="text"
+                        name="organisation"
+                        id="organisation"
+                        autocomplete="organization"
+                        on:input=move |event| organisation.set(event_target_value(&event))
+                    />
+                </label>
+                <label for="email">
+                    Email:
+                    <input
+                        type="email"
+                        name="email"
+                        id="email"
                         required
-                        minlength="6"
+                        on:input=move |event| email.set(event_target_value(&event))
                     />
                 </label>
+                <PasswordInput password=new_password email=email id="pass" label="Password:"/>
                 <input
                     type="submit"
                     class="primary-button"
-                    value="Signup"
-                    disabled=disable_login_btn
+                    value=move || countdown_label("Signup", retry_in.get())
+                    disabled=move || { disable_login_btn.get() || retry_in.get() > 0 }
                 />
 
                 <input
@@ -82,17 +121,15 @@ pub fn SignUp() -> impl IntoView {
                     class="secondary-button"
                     value="LogIn"
                     on:click=move |_| {
-                        use_navigate()("/csr-example-leptos-supabase/login", Default::default());
+                        use_navigate()(
+                            &AppRoute::Login.href_with(&[("next", &next.get_value())]),
+                            Default::default(),
+                        );
                     }
                 />
 
-                <div class="or">OR</div>
-
-                <a type="button" class="login-with-google-btn" href=env::APP_GOOGLE_LOGIN_URL>
-                    "Continue with Google"
-                </a>
+                <OAuthButtons/>
             </form>
         </div>
     }
 }
-
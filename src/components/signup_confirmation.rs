@@ -0,0 +1,69 @@
+use leptos::*;
+use leptos_router::*;
+
+use crate::{
+    app::toast,
+    core::{
+        auth_service::use_auth,
+        helper::{countdown_label, wait_out_rate_limit},
+        routes::AppRoute,
+    },
+};
+
+/// Shown after signing up until the email is confirmed, the confirmation email can be resent
+#[component]
+pub fn SignUpConfirmation() -> impl IntoView {
+    let auth = use_auth();
+    let disable_resend_btn = RwSignal::new(false);
+    // Seconds until a rate-limited request may be sent again
+    let retry_in = RwSignal::new(0u64);
+    let email = use_query_map().with_untracked(|q| q.get("email").cloned().unwrap_or_default());
+    let email = StoredValue::new(email);
+
+    let resend = move || async move {
+        match auth.client().resend_signup_confirmation(&email.get_value()).await {
+            Ok(_) => toast(String::from("A new confirmation email has been sent")),
+            Err(err) => {
+                wait_out_rate_limit(&err, retry_in);
+                toast(format!("Sending Confirmation Email Failed. {err}"));
+            }
+        }
+    };
+
+    view! {
+        <div id="login-container">
+            <div id="login-signup-form">
+                <h1>Confirm Your Email</h1>
+                <p>
+                    "Please confirm your email from the link sent to " <b>{email.get_value()}</b>
+                </p>
+                <input
+                    type="button"
+                    class="secondary-button"
+                    value=move || countdown_label("Resend Email", retry_in.get())
+                    disabled=move || {
+                        disable_resend_btn.get() || retry_in.get() > 0
+                            || email.with_value(String::is_empty)
+                    }
+                    on:click=move |_| {
+                        spawn_local(async move {
+                            disable_resend_btn.set(true);
+                            resend().await;
+                            disable_resend_btn.set(false);
+                        });
+                    }
+                />
+
+                <input
+                    type="button"
+                    class="secondary-button"
+                    value="LogIn"
+                    on:click=move |_| {
+                        use_navigate()(&AppRoute::Login.href(), Default::default());
+                    }
+                />
+
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,110 @@
+use leptos::{html::Input, *};
+use leptos_router::*;
+
+use crate::{
+    app::toast,
+    core::{
+        auth_service::use_auth,
+        helper::{app_url, countdown_label, next_path, redirect_to, wait_out_rate_limit},
+        models::User,
+        routes::AppRoute,
+    },
+};
+
+#[component]
+pub fn VerifyOtp() -> impl IntoView {
+    let auth = use_auth();
+    let code_ref = NodeRef::<Input>::new();
+    let disable_verify_btn = RwSignal::new(false);
+    // Seconds until a rate-limited request may be sent again
+    let retry_in = RwSignal::new(0u64);
+    let email = use_query_map().with_untracked(|q| q.get("email").cloned().unwrap_or_default());
+    let email = StoredValue::new(email);
+
+    let verify = move |code: String| async move {
+        auth.authenticating();
+        let res =
+            auth.client().verify_otp(&email.get_value(), &code).await.and_then(User::try_from);
+        match res {
+            Ok(new_user) => {
+                auth.sign_in(new_user);
+                use_navigate()(&next_path(), Default::default());
+            }
+            Err(err) => {
+                auth.sign_in_failed();
+                wait_out_rate_limit(&err, retry_in);
+                toast(format!("Verification Failed. {err}"));
+            }
+        }
+    };
+    let resend = move || async move {
+        match auth
+            .client()
+            .sign_in_with_otp(&email.get_value(), &redirect_to(&app_url(AppRoute::Redirect)))
+            .await
+        {
+            Ok(_) => toast(String::from("A new login code has been sent")),
+            Err(err) => {
+                wait_out_rate_limit(&err, retry_in);
+                toast(format!("Sending Login Code Failed. {err}"));
+            }
+        }
+    };
+
+    view! {
+        <div id="login-container">
+            <form
+                id="login-signup-form"
+                on:submit=move |event| {
+                    event.prevent_default();
+                    spawn_local(async move {
+                        disable_verify_btn.set(true);
+                        verify(code_ref.get().unwrap().value()).await;
+                        disable_verify_btn.set(false);
+                    });
+                }
+            >
+
+                <h1>Verify</h1>
+                <p>
+                    "Enter the 6-digit code sent to " <b>{email.get_value()}</b>
+                    " or open the link in the email."
+                </p>
+                <label for="code">
+                    Code:
+                    <input
+                        node_ref=code_ref
+                        type="text"
+                        name="code"
+                        id="code"
+                        inputmode="numeric"
+                        autocomplete="one-time-code"
+                        pattern="[0-9]{6}"
+                        maxlength="6"
+                        required
+                    />
+                </label>
+                <input
+                    type="submit"
+                    class="primary-button"
+                    value=move || countdown_label("Verify", retry_in.get())
+                    disabled=move || { disable_verify_btn.get() || retry_in.get() > 0 }
+                />
+                <input
+                    type="button"
+                    class="secondary-button"
+                    value="Resend Code"
+                    disabled=move || { disable_verify_btn.get() || retry_in.get() > 0 }
+                    on:click=move |_| {
+                        spawn_local(async move {
+                            disable_verify_btn.set(true);
+                            resend().await;
+                            disable_verify_btn.set(false);
+                        });
+                    }
+                />
+
+            </form>
+        </div>
+    }
+}
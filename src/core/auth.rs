@@ -0,0 +1,333 @@
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::core::{
+    helper::percent_encode,
+    models::{
+        AuthError, AuthErrorBody, AuthUser, Challenge, LogoutScope, Session, SignUpResponse,
+        TotpEnrollment, UserAttributes, UserMetadata,
+    },
+    providers::OAuthProvider,
+};
+
+/// Typed client for the Supabase auth (GoTrue) REST API living under `[supabase_url]/auth/v1`.
+#[derive(Debug, Clone)]
+pub struct AuthClient {
+    url: String,
+    api_key: String,
+    client: Client,
+}
+
+impl AuthClient {
+    pub fn new(url: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self { url: url.into(), api_key: api_key.into(), client: Client::new() }
+    }
+
+    /// Signs up with the profile fields that end up in `user_metadata`
+    pub async fn sign_up(
+        &self,
+        email: &str,
+        password: &str,
+        metadata: &UserMetadata,
+    ) -> Result<SignUpResponse, AuthError> {
+        let request = self
+            .request(Method::POST, "/signup")
+            .body(json!({"email": email, "password": password, "data": metadata}).to_string());
+        Self::parse(self.send(request).await?).await
+    }
+
+    /// Sends the signup confirmation email again, e.g. when the first one didn't arrive
+    pub async fn resend_signup_confirmation(&self, email: &str) -> Result<(), AuthError> {
+        let request = self
+            .request(Method::POST, "/resend")
+            .body(json!({"type": "signup", "email": email}).to_string());
+        self.send(request).await.map(|_| ())
+    }
+
+    pub async fn sign_in_with_password(
+        &self,
+        email: &str,
+        password: &str,
+    ) -> Result<Session, AuthError> {
+        let request = self
+            .request(Method::POST, "/token?grant_type=password")
+            .body(json!({"email": email, "password": password}).to_string());
+        Self::parse(self.send(request).await?).await
+    }
+
+    /// Sends a one-time code and magic link, the link redirects back to `redirect_to`
+    pub async fn sign_in_with_otp(&self, email: &str, redirect_to: &str) -> Result<(), AuthError> {
+        let request = self
+            .request(Method::POST, &format!("/otp?redirect_to={}", percent_encode(redirect_to)))
+            .body(json!({"email": email, "create_user": false}).to_string());
+        self.send(request).await.map(|_| ())
+    }
+
+    pub async fn verify_otp(&self, email: &str, token: &str) -> Result<Session, AuthError> {
+        let request = self
+            .request(Method::POST, "/verify")
+            .body(json!({"type": "email", "email": email, "token": token}).to_string());
+        Self::parse(self.send(request).await?).await
+    }
+
+    /// URL of the OAuth consent screen, the provider redirects back to `redirect_to` with the
+    /// session, or with an authorization code when a PKCE `code_challenge` is given
+    pub fn authorize_url(
+        &self,
+        provider: OAuthProvider,
+        redirect_to: &str,
+        code_challenge: Option<&str>,
+    ) -> String {
+        let mut url = format!(
+            "{}/authorize?provider={}&redirect_to={}",
+            self.url,
+            provider.id(),
+            percent_encode(redirect_to)
+        );
+        if let Some(code_challenge) = code_challenge {
+            url.push_str(&format!("&code_challenge={code_challenge}&code_challenge_method=s256"));
+        }
+        url
+    }
+
+    pub async fn exchange_code_for_session(
+        &self,
+        auth_code: &str,
+        code_verifier: &str,
+    ) -> Result<Session, AuthError> {
+        let request = self
+            .request(Method::POST, "/token?grant_type=pkce")
+            .body(json!({"auth_code": auth_code, "code_verifier": code_verifier}).to_string());
+        Self::parse(self.send(request).await?).await
+    }
+
+    pub async fn refresh(&self, refresh_token: &str) -> Result<Session, AuthError> {
+        let request = self
+            .request(Method::POST, "/token?grant_type=refresh_token")
+            .body(json!({"refresh_token": refresh_token}).to_string());
+        Self::parse(self.send(request).await?).await
+    }
+
+    /// Revokes the refresh tokens of the sessions in `scope`, access tokens stay valid until they expire
+    pub async fn logout(&self, access_token: &str, scope: LogoutScope) -> Result<(), AuthError> {
+        let request = self
+            .request(Method::POST, &format!("/logout?scope={}", scope.as_str()))
+            .bearer_auth(access_token);
+        self.send(request).await.map(|_| ())
+    }
+
+    /// Sends the password recovery email, its link redirects back to `redirect_to`
+    pub async fn recover(&self, email: &str, redirect_to: &str) -> Result<(), AuthError> {
+        let request = self
+            .request(Method::POST, &format!("/recover?redirect_to={}", percent_encode(redirect_to)))
+            .body(json!({"email": email}).to_string());
+        self.send(request).await.map(|_| ())
+    }
+
+    /// Updates the given attributes, email changes are confirmed by links redirecting to `redirect_to`
+    pub async fn update_user(
+        &self,
+        access_token: &str,
+        attributes: &UserAttributes,
+        redirect_to: Option<&str>,
+    ) -> Result<AuthUser, AuthError> {
+        let path = match redirect_to {
+            Some(redirect_to) => format!("/user?redirect_to={}", percent_encode(redirect_to)),
+            None => String::from("/user"),
+        };
+        let request = self
+            .request(Method::PUT, &path)
+            .bearer_auth(access_token)
+            .body(serde_json::to_string(attributes).map_err(|_| AuthError::JsonParse)?);
+        Self::parse(self.send(request).await?).await
+    }
+
+    pub async fn get_user(&self, access_token: &str) -> Result<AuthUser, AuthError> {
+        let request = self.request(Method::GET, "/user").bearer_auth(access_token);
+        Self::parse(self.send(request).await?).await
+    }
+
+    pub async fn enroll_totp(&self, access_token: &str) -> Result<TotpEnrollment, AuthError> {
+        let request = self
+            .request(Method::POST, "/factors")
+            .bearer_auth(access_token)
+            .body(json!({"factor_type": "totp"}).to_string());
+        Self::parse(self.send(request).await?).await
+    }
+
+    pub async fn challenge_factor(
+        &self,
+        access_token: &str,
+        factor_id: &str,
+    ) -> Result<Challenge, AuthError> {
+        let request = self
+            .request(Method::POST, &format!("/factors/{factor_id}/challenge"))
+            .bearer_auth(access_token);
+        Self::parse(self.send(request).await?).await
+    }
+
+    /// Verifies the code of a challenge, the returned session is `aal2`
+    pub async fn verify_factor(
+        &self,
+        access_token: &str,
+        factor_id: &str,
+        challenge_id: &str,
+        code: &str,
+    ) -> Result<Session, AuthError> {
+        let request = self
+            .request(Method::POST, &format!("/factors/{factor_id}/verify"))
+            .bearer_auth(access_token)
+            .body(json!({"challenge_id": challenge_id, "code": code}).to_string());
+        Self::parse(self.send(request).await?).await
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, format!("{}{}", self.url, path))
+            .header("apikey", &self.api_key)
+            .header("Content-Type", "application/json")
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, AuthError> {
+        let res = request.send().await.map_err(|err| AuthError::Network(err.to_string()))?;
+        if res.status().is_success() {
+            return Ok(res);
+        }
+        // When server expires the token and user needs to relogin (It can happen!):
+        if res.status().as_u16() == 401
+            && res
+                .headers()
+                .get("Www-Authenticate")
+                .and_then(|f| f.to_str().ok().map(|f| f.contains("expired")))
+                .unwrap_or(false)
+        {
+            return Err(AuthError::TokenExpired);
+        }
+        let status = res.status().as_u16();
+        let retry_after = res
+            .headers()
+            .get("Retry-After")
+            .and_then(|f| f.to_str().ok().and_then(|f| f.trim().parse::<u64>().ok()));
+        let body = res.bytes().await.unwrap_or_default();
+        Err(Self::error_from_body(status, retry_after, &body))
+    }
+
+    /// Maps an error response of GoTrue, whichever of its error body formats it has
+    fn error_from_body(status: u16, retry_after: Option<u64>, body: &[u8]) -> AuthError {
+        let body = serde_json::from_slice::<AuthErrorBody>(body).unwrap_or_default();
+        let message = body.message().unwrap_or_else(|| {
+            StatusCode::from_u16(status)
+                .ok()
+                .and_then(|f| f.canonical_reason())
+                .unwrap_or("Nothing")
+                .to_string()
+        });
+        if status == 429 || body.code().is_some_and(|f| f.starts_with("over_")) {
+            return AuthError::RateLimited { retry_after, message };
+        }
+        if let Some(weak_password) = body.weak_password {
+            return AuthError::WeakPassword { reasons: weak_password.reasons, message };
+        }
+        AuthError::Response { status, code: body.code(), message }
+    }
+
+    async fn parse<T: DeserializeOwned>(res: Response) -> Result<T, AuthError> {
+        let bytes = res.bytes().await.map_err(|err| AuthError::Network(err.to_string()))?;
+        serde_json::from_slice::<T>(&bytes).map_err(|_| AuthError::JsonParse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_requests_with_the_api_key() {
+        let client = AuthClient::new("https://example.supabase.co/auth/v1", "anon-key");
+        let request =
+            client.request(Method::POST, "/token?grant_type=refresh_token").build().unwrap();
+        assert_eq!(
+            request.url().as_str(),
+            "https://example.supabase.co/auth/v1/token?grant_type=refresh_token"
+        );
+        assert_eq!(request.headers()["apikey"], "anon-key");
+        assert_eq!(request.headers()["content-type"], "application/json");
+    }
+
+    #[test]
+    fn maps_legacy_error_body() {
+        let body = br#"{"error":"invalid_grant","error_description":"Invalid login credentials"}"#;
+        assert_eq!(
+            AuthClient::error_from_body(400, None, body),
+            AuthError::Response {
+                status: 400,
+                code: Some(String::from("invalid_grant")),
+                message: String::from("Invalid login credentials"),
+            }
+        );
+    }
+
+    #[test]
+    fn maps_error_code_body() {
+        let body =
+            br#"{"code":400,"error_code":"invalid_credentials","msg":"Invalid login credentials"}"#;
+        assert_eq!(
+            AuthClient::error_from_body(400, None, body),
+            AuthError::Response {
+                status: 400,
+                code: Some(String::from("invalid_credentials")),
+                message: String::from("Invalid login credentials"),
+            }
+        );
+    }
+
+    #[test]
+    fn falls_back_to_status_reason() {
+        assert_eq!(
+            AuthClient::error_from_body(500, None, b"<html>"),
+            AuthError::Response {
+                status: 500,
+                code: None,
+                message: String::from("Internal Server Error"),
+            }
+        );
+    }
+
+    #[test]
+    fn maps_rate_limits() {
+        let body = br#"{"msg":"Too many requests"}"#;
+        assert_eq!(
+            AuthClient::error_from_body(429, Some(30), body),
+            AuthError::RateLimited {
+                retry_after: Some(30),
+                message: String::from("Too many requests"),
+            }
+        );
+        let body =
+            br#"{"error_code":"over_email_send_rate_limit","msg":"Email rate limit exceeded"}"#;
+        assert_eq!(
+            AuthClient::error_from_body(400, None, body),
+            AuthError::RateLimited {
+                retry_after: None,
+                message: String::from("Email rate limit exceeded"),
+            }
+        );
+    }
+
+    #[test]
+    fn maps_weak_password() {
+        let body = br#"{
+            "error_code": "weak_password",
+            "msg": "Password is too weak",
+            "weak_password": {"reasons": ["length", "pwned"]}
+        }"#;
+        assert_eq!(
+            AuthClient::error_from_body(422, None, body),
+            AuthError::WeakPassword {
+                reasons: vec![String::from("length"), String::from("pwned")],
+                message: String::from("Password is too weak"),
+            }
+        );
+    }
+}
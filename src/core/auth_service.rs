@@ -0,0 +1,295 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use leptos::{
+    expect_context,
+    leptos_dom::{helpers::TimeoutHandle, logging::console_error},
+    on_cleanup, provide_context, set_timeout_with_handle, spawn_local, Effect, Memo, RwSignal,
+    Signal, SignalGet, SignalSet, SignalUpdate, SignalWith, SignalWithUntracked, StoredValue,
+};
+
+use crate::core::{
+    auth::AuthClient,
+    config::{use_config, AppConfig},
+    helper::{auth_client, local_storage, revoke_session, REFRESH_MARGIN_SECS},
+    locks::with_lock,
+    models::{Aal, AuthError, LogoutScope, User},
+    session::{store_user, stored_user, sync_session},
+};
+
+/// Web Lock held while refreshing the session
+const REFRESH_LOCK: &str = "supabase_auth_refresh";
+
+/// Wait before retrying a failed refresh, doubled for each failure up to `REFRESH_RETRY_MAX_SECS`
+const REFRESH_RETRY_SECS: u64 = 5;
+const REFRESH_RETRY_MAX_SECS: u64 = 300;
+
+/// Local storage key of the user the local data (companies) belongs to
+const DATA_OWNER_KEY: &str = "data_owner";
+
+/// Shown on the login page when the session has expired
+pub const SESSION_EXPIRED: &str = "Your session has expired, please log in again";
+
+/// Where the user is in the auth flow, only the states with a session carry the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthState {
+    /// Not logged in
+    Anonymous,
+    /// Reading the stored session at startup, or waiting for a sign-in request
+    Authenticating,
+    /// Logged in with the second factor
+    Authenticated(User),
+    /// Logged in while the access token is being refreshed
+    Refreshing(User),
+    /// The session can't be refreshed anymore (e.g. it was revoked), so the user has to log in again
+    Expired,
+    /// Logged in with the first factor only, the app is usable after the MFA challenge
+    MfaRequired(User),
+}
+
+impl AuthState {
+    /// The state a session puts the user in, a user without tokens is anonymous
+    pub fn from_user(user: User) -> Self {
+        if user.access_token.is_empty() {
+            AuthState::Anonymous
+        } else if user.claims.aal == Aal::Aal2 {
+            AuthState::Authenticated(user)
+        } else {
+            AuthState::MfaRequired(user)
+        }
+    }
+
+    pub fn user(&self) -> Option<&User> {
+        match self {
+            AuthState::Authenticated(user)
+            | AuthState::Refreshing(user)
+            | AuthState::MfaRequired(user) => Some(user),
+            AuthState::Anonymous | AuthState::Authenticating | AuthState::Expired => None,
+        }
+    }
+}
+
+/// Owns the auth state of the app, components get it with `use_auth` and subscribe to the state
+/// through its methods. The session is kept in the storage chosen with "Remember me".
+#[derive(Debug, Clone, Copy)]
+pub struct AuthService {
+    state: RwSignal<AuthState>,
+    loaded: Signal<bool>,
+    config: StoredValue<AppConfig>,
+    refresh_timer: StoredValue<Option<TimeoutHandle>>,
+    /// Refreshes that failed in a row, e.g. while offline, each one waits twice as long
+    failed_refreshes: StoredValue<u32>,
+}
+
+/// Creates the auth service of the app and provides it as context
+pub fn provide_auth_service() -> AuthService {
+    let state = RwSignal::new(AuthState::Authenticating);
+    let loaded = sync_session(state);
+    let auth = AuthService {
+        state,
+        loaded,
+        config: StoredValue::new(use_config()),
+        refresh_timer: StoredValue::new(None),
+        failed_refreshes: StoredValue::new(0),
+    };
+    // Refresh the access token shortly before it expires, so requests don't have to fail first.
+    // Only a new session moves the timer, not e.g. the start of a refresh.
+    let expires_at = Memo::new(move |_| auth.with_user(|user| user.expires_at).unwrap_or_default());
+    Effect::new(move |_| match expires_at.get() {
+        0 => auth.clear_refresh_timer(),
+        expires_at => {
+            let delay = (expires_at - REFRESH_MARGIN_SECS - Utc::now().timestamp()).max(0);
+            auth.arm_refresh_timer(Duration::from_secs(delay as u64));
+        }
+    });
+    on_cleanup(move || auth.clear_refresh_timer());
+    provide_context(auth);
+    auth
+}
+
+pub fn use_auth() -> AuthService {
+    expect_context::<AuthService>()
+}
+
+impl AuthService {
+    /// Client for the auth endpoint of the configured Supabase project
+    pub fn client(&self) -> AuthClient {
+        self.config.with_value(auth_client)
+    }
+
+    pub fn state(&self) -> Signal<AuthState> {
+        self.state.into()
+    }
+
+    /// Whether the stored session has been read, which takes a moment when the tokens are
+    /// encrypted
+    pub fn loaded(&self) -> bool {
+        self.loaded.get()
+    }
+
+    pub fn is_logged_in(&self) -> bool {
+        self.state.with(|state| state.user().is_some())
+    }
+
+    /// Reads the user of the session, `None` without one
+    pub fn with_user<T>(&self, f: impl FnOnce(&User) -> T) -> Option<T> {
+        self.state.with(|state| state.user().map(f))
+    }
+
+    pub fn with_user_untracked<T>(&self, f: impl FnOnce(&User) -> T) -> Option<T> {
+        self.state.with_untracked(|state| state.user().map(f))
+    }
+
+    pub fn user_untracked(&self) -> Option<User> {
+        self.with_user_untracked(User::clone)
+    }
+
+    /// A sign-in request is on its way, ended by `sign_in` or `sign_in_failed`
+    pub fn authenticating(&self) {
+        self.state.update(|state| {
+            if state.user().is_none() {
+                *state = AuthState::Authenticating;
+            }
+        });
+    }
+
+    pub fn sign_in_failed(&self) {
+        self.state.update(|state| {
+            if *state == AuthState::Authenticating {
+                *state = AuthState::Anonymous;
+            }
+        });
+    }
+
+    /// Starts the session of the user, the local data is cleared when it belongs to another user
+    pub fn sign_in(&self, user: User) {
+        let storage = local_storage();
+        // The owner outlives the session when logging out keeps the local data
+        let data_owner = storage.get_item(DATA_OWNER_KEY).ok().flatten().unwrap_or_else(|| {
+            self.with_user_untracked(|user| user.uuid.clone()).unwrap_or_default()
+        });
+        if data_owner != user.uuid {
+            storage.clear().expect("Can't access to local storage");
+        }
+        storage.set_item(DATA_OWNER_KEY, &user.uuid).expect("Can't access to local storage");
+        self.state.set(AuthState::from_user(user));
+    }
+
+    /// Logs the user out locally right away, the session is revoked on the server in the
+    /// background so logging out works offline too
+    pub fn log_out(&self, scope: LogoutScope) {
+        local_storage().clear().expect("Can't access to local storage");
+        self.end_session(scope);
+    }
+
+    /// Logs the user out like `log_out` but keeps the local data, e.g. unsynced companies, for
+    /// when the same user logs back in
+    pub fn end_session(&self, scope: LogoutScope) {
+        if let Some(old_user) = self.user_untracked() {
+            let auth_client = self.client();
+            spawn_local(async move {
+                if let Err(err) = revoke_session(auth_client, old_user, scope).await {
+                    console_error(format!("Revoking the session failed: {err:?}").as_str());
+                }
+            });
+        }
+        self.state.set(AuthState::Anonymous);
+    }
+
+    /// Drops the session and the local data without revoking anything, e.g. when the account is
+    /// deleted
+    pub fn discard_session(&self) {
+        local_storage().clear().expect("Can't access to local storage");
+        self.state.set(AuthState::Anonymous);
+    }
+
+    /// Access token of the user, refreshed first when it's about to expire
+    pub async fn fresh_access_token(&self) -> Result<String, AuthError> {
+        let user = self.user_untracked().ok_or(AuthError::TokenExpired)?;
+        if user.expires_at - REFRESH_MARGIN_SECS > Utc::now().timestamp() {
+            return Ok(user.access_token);
+        }
+        self.refresh().await.map(|user| user.access_token)
+    }
+
+    /// Refreshes the session, which expires when GoTrue rejects the refresh token. Other errors
+    /// (e.g. being offline) keep the session for another try.
+    pub async fn refresh(&self) -> Result<User, AuthError> {
+        let user = self.user_untracked().ok_or(AuthError::TokenExpired)?;
+        self.state.set(AuthState::Refreshing(user.clone()));
+        let res = refresh_session(self.client(), user.refresh_token.clone()).await;
+        let next_state = match &res {
+            Ok(new_user) => AuthState::from_user(new_user.clone()),
+            Err(AuthError::TokenExpired | AuthError::Response { status: 400..=499, .. }) => {
+                AuthState::Expired
+            }
+            Err(_) => AuthState::from_user(user),
+        };
+        if self.state.with_untracked(|state| matches!(state, AuthState::Refreshing(_))) {
+            self.state.set(next_state);
+        } else if res.is_ok() {
+            // Logging out in the meantime wins over the session stored while refreshing
+            store_user(self.user_untracked().unwrap_or_default()).await;
+        }
+        match &res {
+            Ok(_) => self.failed_refreshes.set_value(0),
+            // The session is kept when the refresh token wasn't rejected, so try again later
+            Err(_) if self.with_user_untracked(|_| ()).is_some() => {
+                self.failed_refreshes.update_value(|f| *f += 1);
+                let backoff = REFRESH_RETRY_SECS << (self.failed_refreshes.get_value() - 1).min(6);
+                self.arm_refresh_timer(Duration::from_secs(backoff.min(REFRESH_RETRY_MAX_SECS)));
+            }
+            Err(_) => {}
+        }
+        res
+    }
+
+    /// Refreshes the session after `delay`, replacing the refresh armed before
+    fn arm_refresh_timer(&self, delay: Duration) {
+        self.clear_refresh_timer();
+        let auth = *self;
+        let handle = set_timeout_with_handle(
+            move || {
+                spawn_local(async move {
+                    // Failures are retried by `refresh` itself
+                    let _ = auth.refresh().await;
+                })
+            },
+            delay,
+        );
+        self.refresh_timer.set_value(handle.ok());
+    }
+
+    fn clear_refresh_timer(&self) {
+        if let Some(handle) = self.refresh_timer.get_value() {
+            handle.clear();
+        }
+    }
+}
+
+/// Refreshes the session, one tab at a time: refresh tokens are rotated and reusing one revokes
+/// the whole session, so a tab that waited for the lock takes the user the other tab stored.
+/// The new session is stored before the lock is released for that reason.
+async fn refresh_session(
+    auth_client: AuthClient,
+    old_refresh_token: String,
+) -> Result<User, AuthError> {
+    with_lock(REFRESH_LOCK, move || async move {
+        let stored_user = stored_user().await;
+        if stored_user.refresh_token != old_refresh_token
+            && !stored_user.access_token.is_empty()
+            && stored_user.expires_at - REFRESH_MARGIN_SECS > Utc::now().timestamp()
+        {
+            return Ok(stored_user);
+        }
+        let mut session = auth_client.refresh(&old_refresh_token).await?;
+        // When server decides to just renew the accesss token not the refresh token:
+        if session.refresh_token.is_empty() {
+            session.refresh_token = old_refresh_token;
+        }
+        let user = User::try_from(session)?;
+        store_user(user.clone()).await;
+        Ok(user)
+    })
+    .await
+}
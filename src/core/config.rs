@@ -0,0 +1,176 @@
+use leptos::expect_context;
+use serde::Deserialize;
+
+use crate::{
+    core::{password::PasswordPolicy, providers::OAuthProvider, routes::base_path},
+    env::BUILD_CONFIG,
+};
+
+/// `<meta name="app-config" content="{...}">` takes precedence over `config.json`
+const CONFIG_META_SELECTOR: &str = "meta[name=app-config][content]";
+const CONFIG_FILE: &str = "config.json";
+
+/// Settings read at startup, so the same build can be deployed to several environments. Fields
+/// that are missing keep the value built in from `.env`, unknown fields are rejected so typos don't
+/// go unnoticed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    /// `APP_PROFILE`, e.g. `dev`, `staging` or `prod`
+    pub profile: String,
+    /// The project URL, e.g. `https://[project_ref].supabase.co`
+    pub supabase_url: String,
+    pub anon_key: String,
+    pub redirect_url: String,
+    /// Comma separated, e.g. `google,github`
+    pub oauth_providers: String,
+    /// Comma separated, e.g. `min_length=8,lowercase,digits`
+    pub password_policy: String,
+    /// `0` disables the idle logout
+    pub idle_timeout_minutes: u32,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            profile: BUILD_CONFIG.profile.to_string(),
+            supabase_url: BUILD_CONFIG.supabase_url.to_string(),
+            anon_key: BUILD_CONFIG.anon_key.to_string(),
+            redirect_url: BUILD_CONFIG.redirect_url.to_string(),
+            oauth_providers: BUILD_CONFIG.oauth_providers.to_string(),
+            password_policy: BUILD_CONFIG.password_policy.to_string(),
+            idle_timeout_minutes: BUILD_CONFIG.idle_timeout_minutes,
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn is_production(&self) -> bool {
+        matches!(self.profile.as_str(), "prod" | "production")
+    }
+
+    pub fn auth_url(&self) -> String {
+        self.endpoint(BUILD_CONFIG.auth_url, "auth/v1")
+    }
+
+    pub fn rest_url(&self) -> String {
+        self.endpoint(BUILD_CONFIG.rest_url, "rest/v1")
+    }
+
+    /// The endpoint checked by `build.rs` unless `config.json` points to another project, so it
+    /// only has to set the project URL
+    fn endpoint(&self, built_in: &str, path: &str) -> String {
+        let supabase_url = self.supabase_url.trim_end_matches('/');
+        if supabase_url == BUILD_CONFIG.supabase_url {
+            built_in.to_string()
+        } else {
+            format!("{supabase_url}/{path}")
+        }
+    }
+
+    /// Checks the values like `build.rs` does for `.env`, returns every invalid one
+    fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for (name, value) in
+            [("supabase_url", &self.supabase_url), ("redirect_url", &self.redirect_url)]
+        {
+            match reqwest::Url::parse(value) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => {}
+                _ => errors.push(format!("`{name}` has to be an http(s) URL, got `{value}`")),
+            }
+        }
+        if let Err(err) = self.password_policy.parse::<PasswordPolicy>() {
+            errors.push(err);
+        }
+        errors.extend(
+            self.oauth_providers
+                .split(',')
+                .filter(|f| !f.trim().is_empty())
+                .filter_map(|f| f.parse::<OAuthProvider>().err()),
+        );
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+/// The config `App` was mounted with
+pub fn use_config() -> AppConfig {
+    expect_context::<AppConfig>()
+}
+
+/// Reads the config from the page or `config.json` next to `index.html`, has to finish before
+/// the app is mounted. Falls back to the compile-time values when neither is there, but a config
+/// that is there and invalid is an error rather than silently ignored.
+pub async fn load_config() -> Result<AppConfig, String> {
+    match meta_config() {
+        Some(json) => parse(&json),
+        None => fetch_config().await.map_or_else(|| Ok(AppConfig::default()), |json| parse(&json)),
+    }
+}
+
+fn parse(json: &str) -> Result<AppConfig, String> {
+    let config: AppConfig = serde_json::from_str(json).map_err(|err| err.to_string())?;
+    config.validate()?;
+    Ok(config)
+}
+
+fn meta_config() -> Option<String> {
+    web_sys::window()?
+        .document()?
+        .query_selector(CONFIG_META_SELECTOR)
+        .ok()??
+        .get_attribute("content")
+}
+
+async fn fetch_config() -> Option<String> {
+    let origin = web_sys::window()?.location().origin().ok()?;
+    let response = reqwest::get(format!("{origin}{}/{CONFIG_FILE}", base_path())).await.ok()?;
+    // Servers falling back to `index.html` for unknown paths answer with HTML instead of a 404
+    let is_json = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|f| f.to_str().ok())
+        .is_some_and(|f| f.contains("json"));
+    if !response.status().is_success() || !is_json {
+        return None;
+    }
+    response.text().await.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_built_in_values_for_missing_fields() {
+        let config = parse(r#"{"profile": "staging", "idle_timeout_minutes": 15}"#).unwrap();
+        assert_eq!(
+            config,
+            AppConfig {
+                profile: "staging".to_string(),
+                idle_timeout_minutes: 15,
+                ..AppConfig::default()
+            }
+        );
+    }
+
+    #[test]
+    fn derives_endpoints_of_other_projects() {
+        assert_eq!(AppConfig::default().auth_url(), BUILD_CONFIG.auth_url);
+        let config = parse(r#"{"supabase_url": "https://other.supabase.co/"}"#).unwrap();
+        assert_eq!(config.auth_url(), "https://other.supabase.co/auth/v1");
+        assert_eq!(config.rest_url(), "https://other.supabase.co/rest/v1");
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        assert!(parse(r#"{"idle_timeout": 15}"#).is_err());
+        assert!(parse(r#"{"idle_timeout_minutes": "15"}"#).is_err());
+        assert!(parse(r#"{"supabase_url": "example.supabase.co"}"#).is_err());
+        assert!(parse(r#"{"password_policy": "min_length=eight"}"#).is_err());
+        assert!(parse(r#"{"oauth_providers": "google,myspace"}"#).is_err());
+    }
+}
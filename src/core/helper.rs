@@ -1,9 +1,23 @@
-use crate::{core::models::RefreshTokenError, core::models::User, env};
+use crate::{
+    core::auth::AuthClient,
+    core::config::AppConfig,
+    core::models::{AuthError, Claims, LogoutScope, RedirectError, User},
+    core::routes::{is_app_path, AppRoute},
+    core::session::{remember_me, set_remember_me},
+};
 use base64::{self, Engine};
-use leptos::{Signal, SignalGet};
-use serde_json::{json, Value};
+use leptos::{set_timeout, RwSignal, SignalGetUntracked, SignalSet, SignalUpdate};
+use postgrest::Postgrest;
+use std::collections::HashMap;
+use std::time::Duration;
 use web_sys::Storage;
 
+/// How many seconds before the access token expiry it gets refreshed
+pub const REFRESH_MARGIN_SECS: i64 = 60;
+
+/// How long forms wait after a rate-limited request when GoTrue doesn't send `Retry-After`
+pub const RATE_LIMIT_FALLBACK_SECS: u64 = 60;
+
 #[inline]
 pub fn local_storage() -> Storage {
     web_sys::window()
@@ -13,135 +27,236 @@ pub fn local_storage() -> Storage {
         .expect("Can't access to local storage")
 }
 
-pub fn url_hash_to_user(mut url_hash: String) -> Option<User> {
-    if url_hash.is_empty() {
-        return None;
-    }
-    let mut access_token = None;
-    let mut refresh_token = None;
-    url_hash.remove(0);
-    for q in url_hash.split("&") {
-        let Some((key, value)) = q.split_once("=") else {
-            break;
+/// Splits a `#key=value&...` URL hash (or a `?key=value&...` query) into its decoded parameters
+pub fn url_params(url_part: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    for q in url_part.trim_start_matches(['#', '?']).split('&') {
+        let Some((key, value)) = q.split_once('=') else {
+            continue;
         };
-        if key == "access_token" {
-            access_token = Some(value.to_owned());
-        } else if key == "refresh_token" {
-            refresh_token = Some(value.to_owned());
+        params.insert(percent_decode(key), percent_decode(value));
+    }
+    params
+}
+
+pub fn url_hash_to_user(url_hash: String) -> Result<User, RedirectError> {
+    let mut params = url_params(&url_hash);
+    if let Some(err) = RedirectError::from_params(&mut params) {
+        return Err(err);
+    }
+    match (params.remove("access_token"), params.remove("refresh_token")) {
+        (Some(access_token), Some(refresh_token)) => {
+            User::from_tokens(access_token, refresh_token).ok_or(RedirectError::MissingSession)
+        }
+        _ => Err(RedirectError::MissingSession),
+    }
+}
+
+/// The `next` query of the current page, for where to go after logging in
+fn current_next() -> Option<String> {
+    let search = web_sys::window()
+        .expect("Can't access to the window")
+        .location()
+        .search()
+        .unwrap_or_default();
+    url_params(&search)
+        .remove("next")
+        // Only paths of this app, so the parameter can't send users to another site
+        .filter(|f| is_app_path(f))
+}
+
+/// Where to go after logging in: the `next` query of the current page, which `redirect_to`
+/// passes on to `/redirect`
+pub fn next_path() -> String {
+    current_next().unwrap_or_else(|| AppRoute::Home.href())
+}
+
+/// Query parameter of `redirect_to` URLs carrying an unchecked "Remember me"
+const REMEMBER_PARAM: &str = "remember";
+
+/// `redirect_to` URL for auth emails and OAuth providers. Email links open in a new tab, so
+/// what the login page knows goes along in the URL: the `next` page and an unchecked
+/// "Remember me".
+pub fn redirect_to(url: &str) -> String {
+    let mut query = Vec::new();
+    if let Some(next) = current_next() {
+        query.push(format!("next={}", percent_encode(&next)));
+    }
+    if !remember_me() {
+        query.push(format!("{REMEMBER_PARAM}=0"));
+    }
+    if query.is_empty() {
+        return url.to_string();
+    }
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}{}", query.join("&"))
+}
+
+/// Applies the "Remember me" choice a `redirect_to` URL carries, before the session is stored
+pub fn restore_remember_me(query: &HashMap<String, String>) {
+    if query.get(REMEMBER_PARAM).is_some_and(|f| f == "0") {
+        set_remember_me(false);
+    }
+}
+
+/// Absolute URL of a page of this app, e.g. for the `redirect_to` of auth emails
+pub fn app_url(route: AppRoute) -> String {
+    let origin = web_sys::window()
+        .expect("Can't access to the window")
+        .location()
+        .origin()
+        .expect("Can't access to the location");
+    format!("{origin}{}", route.href())
+}
+
+pub fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
         }
     }
-    let uuid_email = access_token
-        .as_ref()
-        .map(|access_token| access_token_to_uuid_email(access_token.as_str()))
-        .flatten();
-    match (uuid_email, access_token, refresh_token) {
-        (Some((uuid, email)), Some(access_token), Some(refresh_token)) => {
-            Some(User { uuid, email, access_token, refresh_token })
+    encoded
+}
+
+/// Decodes `%XX` escapes and `+` as space, as GoTrue sends them in redirect URLs
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            // `from_str_radix` alone would take a sign too, e.g. `%+1`
+            b'%' if i + 2 < bytes.len()
+                && bytes[i + 1].is_ascii_hexdigit()
+                && bytes[i + 2].is_ascii_hexdigit() =>
+            {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+                decoded.push(u8::from_str_radix(hex, 16).unwrap_or_default());
+                i += 2;
+            }
+            b'%' => decoded.push(b'%'),
+            byte => decoded.push(byte),
         }
-        _ => None,
+        i += 1;
     }
+    String::from_utf8_lossy(&decoded).into_owned()
 }
-pub fn access_token_to_uuid_email(token: &str) -> Option<(String, String)> {
+
+pub fn access_token_to_claims(token: &str) -> Option<Claims> {
     if token.is_empty() {
         return None;
     }
     let output_size = base64::decoded_len_estimate(token.len());
     let mut payload_buffer = Vec::<u8>::with_capacity(output_size);
-    let payload_base64 = token.split(".").nth(1)?;
+    let payload_base64 = token.split('.').nth(1)?;
     base64::engine::general_purpose::URL_SAFE_NO_PAD
         .decode_vec(payload_base64, &mut payload_buffer)
         .ok()?;
-    let payload_json: Value = serde_json::from_slice(&payload_buffer[..]).ok()?;
-    let uuid = payload_json.get("sub")?.as_str()?.to_owned();
-    let email = payload_json.get("email")?.as_str()?.to_owned();
-    Some((uuid, email))
-}
-
-pub async fn refresh_token(user: Signal<User>) -> Result<User, RefreshTokenError> {
-    let client = reqwest::Client::new();
-    let body = json!({"refresh_token": user.get().refresh_token}).to_string();
-    let res = client
-        .post(env::APP_REFRESH_TOKEN_URL)
-        .body(body)
-        .header("apikey", env::APP_API_KEY)
-        .send()
-        .await;
-    match res {
-        Ok(res) => {
-            if res.status().is_success() {
-                let user_details = res
-                    .bytes()
-                    .await
-                    .ok()
-                    .map(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
-                    .flatten();
-
-                match user_details {
-                    Some(user_details) => {
-                        let access_token = user_details
-                            .get("access_token")
-                            .as_ref()
-                            .map(|access_token| access_token.as_str())
-                            .flatten()
-                            .map(str::to_string);
-                        let refresh_token = user_details
-                            .get("refresh_token")
-                            .as_ref()
-                            .map(|refresh_token| refresh_token.as_str())
-                            .flatten()
-                            .map(str::to_string);
-                        let uuid_email = access_token
-                            .as_ref()
-                            .map(|access_token| access_token_to_uuid_email(access_token.as_str()))
-                            .flatten();
-
-                        match (access_token, refresh_token, uuid_email) {
-                            (Some(access_token), Some(refresh_token), Some((uuid, email))) => {
-                                return Ok(User { access_token, uuid, refresh_token, email });
-                            }
-                            // When server decides to just renew the accesss token not the refresh token:
-                            (Some(access_token), None, Some((uuid, email))) => {
-                                // local_storage().set("token", access_token).expect("Can't write to local storage");
-                                return Ok(User {
-                                    access_token,
-                                    uuid,
-                                    email,
-                                    refresh_token: user.get().refresh_token,
-                                });
-                            }
-                            // When server expires the refresh token and user needs to relogin (It hardly happens because then the response wouldn't be successfull at all):
-                            (None, None, None) => {
-                                return Err(RefreshTokenError::RefreshTokenExpirationError);
-                            }
-                            _ => {
-                                return Err(RefreshTokenError::UnknownError);
-                            }
-                        }
-                    }
-                    // Parse problem!
-                    None => {
-                        return Err(RefreshTokenError::JsonParseError);
-                    }
-                }
-            } else {
-                if res.status().as_u16() == 401
-                    && res
-                        .headers()
-                        .get("Www-Authenticate")
-                        .and_then(|f| f.to_str().ok().map(|f| f.contains("expired")))
-                        .unwrap_or(false)
-                {
-                    // When server expires the refresh token and user needs to relogin (It can happen!):
-                    return Err(RefreshTokenError::RefreshTokenExpirationError);
-                } else {
-                    // Regular internet problems
-                    return Err(RefreshTokenError::NetworkError);
-                }
-            }
-        }
-        Err(_) => {
-            return Err(RefreshTokenError::NetworkError);
+    serde_json::from_slice(&payload_buffer[..]).ok()
+}
+
+pub fn postgrest_client(config: &AppConfig) -> Postgrest {
+    Postgrest::new(config.rest_url()).insert_header("apikey", &config.anon_key)
+}
+
+pub fn auth_client(config: &AppConfig) -> AuthClient {
+    AuthClient::new(config.auth_url(), &config.anon_key)
+}
+
+/// Revokes the session of a user who is logging out, refreshing the access token first when it
+/// has already expired (the refreshed session gets revoked right away so it doesn't need storing)
+pub async fn revoke_session(
+    auth_client: AuthClient,
+    user: User,
+    scope: LogoutScope,
+) -> Result<(), AuthError> {
+    match auth_client.logout(&user.access_token, scope).await {
+        Err(AuthError::TokenExpired | AuthError::Response { status: 401, .. }) => {
+            let session = auth_client.refresh(&user.refresh_token).await?;
+            auth_client.logout(&session.access_token, scope).await
         }
+        res => res,
     }
 }
 
+/// Starts counting `retry_in` down to zero once per second when the request was rate-limited, so
+/// forms can keep their submit button disabled until then
+pub fn wait_out_rate_limit(err: &AuthError, retry_in: RwSignal<u64>) {
+    let AuthError::RateLimited { retry_after, .. } = err else {
+        return;
+    };
+    let already_counting = retry_in.get_untracked() > 0;
+    retry_in.set(retry_after.unwrap_or(RATE_LIMIT_FALLBACK_SECS));
+    if !already_counting {
+        count_down(retry_in);
+    }
+}
+
+fn count_down(retry_in: RwSignal<u64>) {
+    set_timeout(
+        move || {
+            // Stops when the form is gone
+            let remaining = retry_in.try_update(|secs| {
+                *secs = secs.saturating_sub(1);
+                *secs
+            });
+            if remaining.is_some_and(|secs| secs > 0) {
+                count_down(retry_in);
+            }
+        },
+        Duration::from_secs(1),
+    );
+}
+
+/// Button label showing how long a rate-limited form still has to wait, e.g. `LogIn (42s)`
+pub fn countdown_label(label: &str, retry_in: u64) -> String {
+    match retry_in {
+        0 => label.to_string(),
+        secs => format!("{label} ({secs}s)"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_url_params() {
+        let params = url_params(
+            "#access_token=abc&type=recovery&error_description=Email+link%20is%2Finvalid",
+        );
+        assert_eq!(params.get("access_token").map(String::as_str), Some("abc"));
+        assert_eq!(params.get("type").map(String::as_str), Some("recovery"));
+        assert_eq!(
+            params.get("error_description").map(String::as_str),
+            Some("Email link is/invalid")
+        );
+        assert_eq!(
+            url_params("?next=%2Fsettings").get("next").map(String::as_str),
+            Some("/settings")
+        );
+        assert!(url_params("").is_empty());
+    }
+
+    #[test]
+    fn decodes_only_hex_escapes() {
+        assert_eq!(percent_decode("%C3%A9t%C3%A9"), "été");
+        assert_eq!(percent_decode("%+1"), "% 1");
+        assert_eq!(percent_decode("%-f"), "%-f");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%4"), "%4");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn encodes_what_it_decodes() {
+        let value = "a b/c?d=e&f+é~";
+        assert_eq!(percent_encode(value), "a%20b%2Fc%3Fd%3De%26f%2B%C3%A9~");
+        assert_eq!(percent_decode(&percent_encode(value)), value);
+    }
+}
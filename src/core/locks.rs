@@ -0,0 +1,41 @@
+use std::future::Future;
+
+use futures::channel::oneshot;
+use js_sys::{Function, Promise, Reflect};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+
+/// Runs `f` while holding the Web Lock `name`, so only one tab of the app runs it at a time.
+/// Browsers without the Web Locks API (or insecure contexts) run it right away.
+pub async fn with_lock<F, Fut, T>(name: &str, f: F) -> T
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = T>,
+{
+    let Some(release) = acquire(name).await else {
+        return f().await;
+    };
+    let output = f().await;
+    release.call0(&JsValue::NULL).unwrap_or_default();
+    output
+}
+
+/// Waits for the lock and returns the function releasing it
+async fn acquire(name: &str) -> Option<Function> {
+    let navigator = web_sys::window()?.navigator();
+    let locks = Reflect::get(&navigator, &JsValue::from_str("locks")).ok()?;
+    let request =
+        Reflect::get(&locks, &JsValue::from_str("request")).ok()?.dyn_into::<Function>().ok()?;
+
+    // The lock is held until the promise returned by the callback settles
+    let mut release = None;
+    let held = Promise::new(&mut |resolve, _| release = Some(resolve));
+    let release = release?;
+    let (acquired_tx, acquired_rx) = oneshot::channel::<()>();
+    let callback = Closure::once_into_js(move |_lock: JsValue| {
+        acquired_tx.send(()).unwrap_or_default();
+        held
+    });
+    request.call2(&locks, &JsValue::from_str(name), &callback).ok()?;
+    acquired_rx.await.ok()?;
+    Some(release)
+}
@@ -1,3 +1,12 @@
+pub mod auth;
+pub mod auth_service;
+pub mod config;
 pub mod helper;
+pub mod locks;
 pub mod models;
-
+pub mod password;
+pub mod pkce;
+pub mod providers;
+pub mod routes;
+pub mod session;
+pub mod token_crypto;
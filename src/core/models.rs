@@ -1,8 +1,13 @@
 use chrono::{DateTime, Utc};
 use leptos::{RwSignal, SignalGetUntracked};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
 use std::fmt;
 
+use crate::core::helper::access_token_to_claims;
+
+#[allow(clippy::derived_hash_with_manual_eq)]
 #[derive(Debug, Clone, Eq, Serialize, Deserialize, Hash)]
 pub struct Company {
     pub name: RwSignal<String>,
@@ -32,25 +37,405 @@ pub struct Job {
     pub qualification: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub enum Failure {
-    AccessTokenExpired,
-    RefreshTokenExpired,
-    Other,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    Network(String),
+    JsonParse,
+    InvalidToken,
+    TokenExpired,
+    /// Too many requests, e.g. emails sent to the same address, `retry_after` is in seconds
+    RateLimited {
+        retry_after: Option<u64>,
+        message: String,
+    },
+    /// The password doesn't meet the requirements of the project, e.g. `length` or `pwned`
+    WeakPassword {
+        reasons: Vec<String>,
+        message: String,
+    },
+    Response {
+        status: u16,
+        code: Option<String>,
+        message: String,
+    },
+}
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthError::Network(err) => write!(f, "Error: {err}"),
+            AuthError::JsonParse => write!(f, "Response could not be parsed"),
+            AuthError::InvalidToken => write!(f, "User token is not valid"),
+            AuthError::TokenExpired => write!(f, "Session has expired"),
+            AuthError::RateLimited { retry_after: Some(secs), message } => {
+                write!(f, "{message}, please try again in {secs} seconds")
+            }
+            AuthError::RateLimited { retry_after: None, message } => {
+                write!(f, "{message}, please try again later")
+            }
+            AuthError::WeakPassword { message, .. } => write!(f, "{message}"),
+            AuthError::Response { message, .. } => write!(f, "{message}"),
+        }
+    }
+}
+
+/// Error body of GoTrue, older versions send `error` and `error_description`, newer ones `msg`
+/// and `error_code`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct AuthErrorBody {
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+    pub msg: Option<String>,
+    pub message: Option<String>,
+    pub error_code: Option<String>,
+    pub weak_password: Option<WeakPassword>,
+}
+impl AuthErrorBody {
+    /// The most descriptive message of the body
+    pub fn message(&self) -> Option<String> {
+        self.msg
+            .clone()
+            .or_else(|| self.error_description.clone())
+            .or_else(|| self.message.clone())
+            .or_else(|| self.error.clone())
+    }
+
+    /// Machine readable code like `invalid_credentials` or `over_email_send_rate_limit`
+    pub fn code(&self) -> Option<String> {
+        self.error_code.clone().or_else(|| self.error.clone())
+    }
+}
+
+/// Why GoTrue rejected a new password
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct WeakPassword {
+    #[serde(default)]
+    pub reasons: Vec<String>,
+}
+
+/// Why `/redirect` (or another page auth links lead to) couldn't log the user in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedirectError {
+    /// GoTrue or the OAuth provider sent back an error, e.g. a denied consent or an expired link
+    Returned {
+        error: String,
+        error_code: Option<String>,
+        description: String,
+    },
+    MissingSession,
+}
+impl RedirectError {
+    /// Takes the `error`, `error_code` and `error_description` parameters of the redirect URL
+    pub fn from_params(params: &mut HashMap<String, String>) -> Option<Self> {
+        let error = params.remove("error")?;
+        Some(RedirectError::Returned {
+            error,
+            error_code: params.remove("error_code"),
+            description: params.remove("error_description").unwrap_or_default(),
+        })
+    }
+}
+impl fmt::Display for RedirectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RedirectError::Returned { error, description, .. } if description.is_empty() => {
+                write!(f, "{error}")
+            }
+            RedirectError::Returned { description, .. } => write!(f, "{description}"),
+            RedirectError::MissingSession => write!(f, "The link doesn't contain a valid session"),
+        }
+    }
+}
+
+/// Token response of GoTrue (`/token`, `/verify`, ...)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Session {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    // Server may decide to just renew the access token not the refresh token
+    #[serde(default)]
+    pub refresh_token: String,
+    pub user: AuthUser,
+}
+
+/// User object of GoTrue (`/user`, `/signup`, ...)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AuthUser {
+    pub id: String,
+    #[serde(default)]
+    pub aud: String,
+    #[serde(default)]
+    pub role: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub app_metadata: Value,
+    #[serde(default)]
+    pub user_metadata: Value,
+    #[serde(default)]
+    pub identities: Option<Vec<Value>>,
+    #[serde(default)]
+    pub factors: Option<Vec<Factor>>,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+impl AuthUser {
+    /// Factors that can be challenged to reach `aal2`
+    pub fn verified_totp_factors(&self) -> Vec<Factor> {
+        self.factors
+            .iter()
+            .flatten()
+            .filter(|f| f.factor_type == "totp" && f.status == "verified")
+            .cloned()
+            .collect()
+    }
+
+    /// Signing up an already registered email returns an obfuscated user without identities
+    pub fn is_already_registered(&self) -> bool {
+        self.identities.as_ref().is_some_and(Vec::is_empty)
+    }
+}
+
+/// An MFA factor of the user
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Factor {
+    pub id: String,
+    #[serde(default)]
+    pub friendly_name: Option<String>,
+    pub factor_type: String,
+    /// `verified` or `unverified`
+    pub status: String,
+}
+
+/// Response of `POST /factors`, the QR code is an SVG data URL for authenticator apps
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TotpEnrollment {
+    pub id: String,
+    pub totp: TotpDetails,
+}
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TotpDetails {
+    pub qr_code: String,
+    pub secret: String,
+    pub uri: String,
+}
+
+/// Response of `POST /factors/{id}/challenge`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Challenge {
+    pub id: String,
+    #[serde(default)]
+    pub expires_at: i64,
+}
+
+/// Which sessions of the user `/logout` revokes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogoutScope {
+    /// Only the current session
+    Local,
+    /// All the sessions except the current one
+    Others,
+    /// All the sessions including the current one
+    Global,
+}
+impl LogoutScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogoutScope::Local => "local",
+            LogoutScope::Others => "others",
+            LogoutScope::Global => "global",
+        }
+    }
+}
+
+/// Body of `PUT /user`, only the provided fields get updated
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UserAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+/// `/signup` returns a session when auto-confirm is enabled, otherwise just the user
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum SignUpResponse {
+    Session(Session),
+    User(AuthUser),
+}
+
+/// Authenticator assurance level of the session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Aal {
+    #[default]
+    Aal1,
+    Aal2,
+}
+impl fmt::Display for Aal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Aal::Aal1 => write!(f, "AAL1"),
+            Aal::Aal2 => write!(f, "AAL2"),
+        }
+    }
+}
+
+/// An entry of the `amr` claim: the method used to authenticate and when it happened
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthMethod {
+    pub method: String,
+    #[serde(default)]
+    pub timestamp: i64,
 }
 
-pub enum RefreshTokenError {
-    NetworkError,
-    JsonParseError,
-    RefreshTokenExpirationError,
-    UnknownError,
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppMetadata {
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub providers: Vec<String>,
 }
+
+/// Payload of the Supabase access token
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    // Phone-only users get an empty email and anonymous users get neither
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub role: String,
+    #[serde(default)]
+    pub aud: String,
+    pub exp: i64,
+    #[serde(default)]
+    pub iat: i64,
+    #[serde(default)]
+    pub aal: Aal,
+    #[serde(default)]
+    pub amr: Vec<AuthMethod>,
+    #[serde(default)]
+    pub app_metadata: AppMetadata,
+    #[serde(default)]
+    pub user_metadata: Value,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub is_anonymous: bool,
+}
+
+impl Claims {
+    /// The provider the user has signed up with (`email`, `phone`, `google`, ...)
+    pub fn provider(&self) -> &str {
+        self.app_metadata.provider.as_deref().unwrap_or(if self.is_anonymous {
+            "anonymous"
+        } else {
+            "unknown"
+        })
+    }
+
+    /// The known fields of `user_metadata`
+    pub fn metadata(&self) -> UserMetadata {
+        serde_json::from_value(self.user_metadata.clone()).unwrap_or_default()
+    }
+}
+
+/// Profile fields kept in `user_metadata`, given at signup or filled by the OAuth provider
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserMetadata {
+    #[serde(
+        default,
+        deserialize_with = "empty_string_as_none",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub full_name: Option<String>,
+    /// Set by some OAuth providers instead of `full_name`
+    #[serde(
+        default,
+        deserialize_with = "empty_string_as_none",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub name: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "empty_string_as_none",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub organisation: Option<String>,
+}
+
+fn empty_string_as_none<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.filter(|f| !f.is_empty()))
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct User {
     pub access_token: String,
     pub uuid: String,
     pub refresh_token: String,
+    /// Email, or the phone number for phone-only users
     pub email: String,
+    /// Unix timestamp (in seconds) when the access token expires
+    #[serde(default)]
+    pub expires_at: i64,
+    #[serde(default)]
+    pub claims: Claims,
+}
+
+impl User {
+    pub fn from_tokens(access_token: String, refresh_token: String) -> Option<User> {
+        let claims = access_token_to_claims(&access_token)?;
+        Some(User {
+            access_token,
+            uuid: claims.sub.clone(),
+            refresh_token,
+            email: claims.email.clone().or(claims.phone.clone()).unwrap_or_default(),
+            expires_at: claims.exp,
+            claims,
+        })
+    }
+
+    /// Full name from the metadata, falling back to the email
+    pub fn display_name(&self) -> String {
+        let metadata = self.claims.metadata();
+        metadata.full_name.or(metadata.name).unwrap_or_else(|| self.email.clone())
+    }
+
+    /// Up to two initials of the display name for the avatar
+    pub fn initials(&self) -> String {
+        let display_name = self.display_name();
+        let initials = if display_name.contains('@') {
+            display_name.chars().take(1).collect::<String>()
+        } else {
+            display_name.split_whitespace().filter_map(|f| f.chars().next()).take(2).collect()
+        };
+        initials.to_uppercase()
+    }
+}
+
+impl TryFrom<Session> for User {
+    type Error = AuthError;
+    fn try_from(session: Session) -> Result<Self, Self::Error> {
+        User::from_tokens(session.access_token, session.refresh_token)
+            .ok_or(AuthError::InvalidToken)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
@@ -77,3 +462,120 @@ impl fmt::Display for Status {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use serde_json::json;
+
+    use super::*;
+
+    /// An unsigned token, only the payload is read on the client
+    fn token(claims: Value) -> String {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        format!(
+            "{}.{}.signature",
+            engine.encode(r#"{"alg":"HS256","typ":"JWT"}"#),
+            engine.encode(claims.to_string())
+        )
+    }
+
+    fn session_json(access_token: &str) -> Value {
+        json!({
+            "access_token": access_token,
+            "token_type": "bearer",
+            "expires_in": 3600,
+            "expires_at": 1_700_003_600,
+            "refresh_token": "refresh",
+            "user": {"id": "user-id", "email": "jane@example.com", "identities": [{}]},
+        })
+    }
+
+    #[test]
+    fn deserializes_session() {
+        let session: Session = serde_json::from_value(session_json(&token(json!({
+            "sub": "user-id",
+            "exp": 1_700_003_600,
+        }))))
+        .unwrap();
+        assert_eq!(session.expires_at, Some(1_700_003_600));
+        assert_eq!(session.user.id, "user-id");
+        let user = User::try_from(session).unwrap();
+        assert_eq!(user.refresh_token, "refresh");
+
+        let session: Session = serde_json::from_value(session_json("not-a-token")).unwrap();
+        assert_eq!(User::try_from(session), Err(AuthError::InvalidToken));
+    }
+
+    #[test]
+    fn decodes_claims() {
+        let access_token = token(json!({
+            "sub": "user-id",
+            "email": "jane@example.com",
+            "phone": "",
+            "role": "authenticated",
+            "exp": 1_700_003_600,
+            "aal": "aal2",
+            "amr": [{"method": "password", "timestamp": 1_700_000_000}],
+            "app_metadata": {"provider": "email", "providers": ["email"]},
+            "user_metadata": {"full_name": "Jane Doe", "organisation": ""},
+        }));
+        let user = User::from_tokens(access_token, String::from("refresh")).unwrap();
+        assert_eq!(user.uuid, "user-id");
+        assert_eq!(user.email, "jane@example.com");
+        assert_eq!(user.expires_at, 1_700_003_600);
+        assert_eq!(user.claims.phone, None);
+        assert_eq!(user.claims.aal, Aal::Aal2);
+        assert_eq!(user.claims.provider(), "email");
+        assert_eq!(user.display_name(), "Jane Doe");
+        assert_eq!(user.claims.metadata().organisation, None);
+    }
+
+    #[test]
+    fn phone_users_go_by_their_number() {
+        let access_token =
+            token(json!({"sub": "user-id", "email": "", "phone": "15550100", "exp": 1}));
+        let user = User::from_tokens(access_token, String::new()).unwrap();
+        assert_eq!(user.email, "15550100");
+        assert_eq!(user.claims.aal, Aal::Aal1);
+        assert_eq!(user.claims.provider(), "unknown");
+    }
+
+    #[test]
+    fn rejects_invalid_tokens() {
+        assert_eq!(User::from_tokens(String::new(), String::new()), None);
+        assert_eq!(User::from_tokens(String::from("not-a-token"), String::new()), None);
+        assert_eq!(User::from_tokens(token(json!({"email": "a@b.c"})), String::new()), None);
+    }
+
+    #[test]
+    fn deserializes_sign_up_response() {
+        let response: SignUpResponse =
+            serde_json::from_value(session_json(&token(json!({"sub": "user-id", "exp": 1}))))
+                .unwrap();
+        assert!(matches!(response, SignUpResponse::Session(_)));
+
+        let response: SignUpResponse = serde_json::from_value(json!({
+            "id": "user-id",
+            "email": "jane@example.com",
+            "identities": [{"provider": "email"}],
+        }))
+        .unwrap();
+        assert!(matches!(&response, SignUpResponse::User(user) if !user.is_already_registered()));
+
+        let response: SignUpResponse =
+            serde_json::from_value(json!({"id": "obfuscated", "identities": []})).unwrap();
+        assert!(matches!(&response, SignUpResponse::User(user) if user.is_already_registered()));
+    }
+
+    #[test]
+    fn reads_error_bodies() {
+        let body: AuthErrorBody =
+            serde_json::from_str(r#"{"error":"invalid_grant","error_description":"Bad"}"#).unwrap();
+        assert_eq!(body.message().as_deref(), Some("Bad"));
+        assert_eq!(body.code().as_deref(), Some("invalid_grant"));
+        let body: AuthErrorBody =
+            serde_json::from_str(r#"{"error_code":"otp_expired","msg":"Expired"}"#).unwrap();
+        assert_eq!(body.message().as_deref(), Some("Expired"));
+        assert_eq!(body.code().as_deref(), Some("otp_expired"));
+    }
+}
@@ -0,0 +1,273 @@
+use std::{fmt, str::FromStr};
+
+use leptos::logging::warn;
+
+use crate::{core::config::AppConfig, env::BUILD_CONFIG};
+
+/// Shorter local parts of the email, e.g. `jo@`, would rule out too many passwords for `no_email`
+const MIN_EMAIL_NAME_LENGTH: usize = 4;
+
+/// Requirements for new passwords, configured by the comma separated `APP_PASSWORD_POLICY`,
+/// e.g. `min_length=8,lowercase,uppercase,digits,symbols,no_email`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    /// The password can't contain the local part of the email, unless it's very short
+    pub no_email: bool,
+}
+impl Default for PasswordPolicy {
+    /// Same as the default of GoTrue
+    fn default() -> Self {
+        Self {
+            min_length: 6,
+            lowercase: false,
+            uppercase: false,
+            digits: false,
+            symbols: false,
+            no_email: false,
+        }
+    }
+}
+impl FromStr for PasswordPolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policy = PasswordPolicy::default();
+        for rule in s.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            match rule.split_once('=') {
+                Some(("min_length", value)) => {
+                    policy.min_length = value
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid password min_length: {value}"))?;
+                }
+                None if rule == "lowercase" => policy.lowercase = true,
+                None if rule == "uppercase" => policy.uppercase = true,
+                None if rule == "digits" => policy.digits = true,
+                None if rule == "symbols" => policy.symbols = true,
+                None if rule == "no_email" => policy.no_email = true,
+                _ => return Err(format!("Unknown password rule: {rule}")),
+            }
+        }
+        Ok(policy)
+    }
+}
+impl PasswordPolicy {
+    /// Rules of the policy that the password breaks, empty when it can be used
+    pub fn issues(&self, password: &str, email: &str) -> Vec<PasswordIssue> {
+        let mut issues = Vec::new();
+        if password.chars().count() < self.min_length {
+            issues.push(PasswordIssue::TooShort(self.min_length));
+        }
+        if self.lowercase && !password.chars().any(|c| c.is_lowercase()) {
+            issues.push(PasswordIssue::MissingLowercase);
+        }
+        if self.uppercase && !password.chars().any(|c| c.is_uppercase()) {
+            issues.push(PasswordIssue::MissingUppercase);
+        }
+        if self.digits && !password.chars().any(|c| c.is_ascii_digit()) {
+            issues.push(PasswordIssue::MissingDigit);
+        }
+        if self.symbols && !password.chars().any(is_symbol) {
+            issues.push(PasswordIssue::MissingSymbol);
+        }
+        let email_name = email.split('@').next().unwrap_or_default().to_lowercase();
+        if self.no_email
+            && email_name.chars().count() >= MIN_EMAIL_NAME_LENGTH
+            && password.to_lowercase().contains(&email_name)
+        {
+            issues.push(PasswordIssue::ContainsEmail);
+        }
+        issues
+    }
+
+    /// Maps the `weak_password` reasons of GoTrue back onto the rules of the policy
+    pub fn issues_from_reasons(&self, reasons: &[String]) -> Vec<PasswordIssue> {
+        reasons
+            .iter()
+            .flat_map(|reason| match reason.as_str() {
+                "length" => vec![PasswordIssue::TooShort(self.min_length)],
+                "characters" => vec![PasswordIssue::MissingCharacters],
+                "pwned" => vec![PasswordIssue::Pwned],
+                _ => vec![],
+            })
+            .collect()
+    }
+}
+
+/// A password policy rule that isn't met
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordIssue {
+    TooShort(usize),
+    MissingLowercase,
+    MissingUppercase,
+    MissingDigit,
+    MissingSymbol,
+    ContainsEmail,
+    /// GoTrue requires more character classes than the client side policy knows about
+    MissingCharacters,
+    /// The password is known from data breaches
+    Pwned,
+}
+impl fmt::Display for PasswordIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PasswordIssue::TooShort(min) => write!(f, "At least {min} characters"),
+            PasswordIssue::MissingLowercase => write!(f, "A lowercase letter"),
+            PasswordIssue::MissingUppercase => write!(f, "An uppercase letter"),
+            PasswordIssue::MissingDigit => write!(f, "A digit"),
+            PasswordIssue::MissingSymbol => write!(f, "A symbol"),
+            PasswordIssue::ContainsEmail => write!(f, "Not containing your email"),
+            PasswordIssue::MissingCharacters => write!(f, "More kinds of characters"),
+            PasswordIssue::Pwned => write!(f, "Not known from a data breach"),
+        }
+    }
+}
+
+/// Rough estimate of how hard the password is to guess
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PasswordStrength {
+    Weak,
+    Fair,
+    Good,
+    Strong,
+}
+impl PasswordStrength {
+    /// Scores the length and the number of character classes used
+    pub fn of(password: &str) -> Self {
+        let length = password.chars().count();
+        let classes = [
+            password.chars().any(|c| c.is_lowercase()),
+            password.chars().any(|c| c.is_uppercase()),
+            password.chars().any(|c| c.is_ascii_digit()),
+            password.chars().any(is_symbol),
+        ]
+        .into_iter()
+        .filter(|f| *f)
+        .count();
+        let length_score = match length {
+            0..=7 => 0,
+            8..=11 => 1,
+            12..=15 => 2,
+            _ => 3,
+        };
+        match length_score + classes {
+            0..=2 => PasswordStrength::Weak,
+            3..=4 => PasswordStrength::Fair,
+            5 => PasswordStrength::Good,
+            _ => PasswordStrength::Strong,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PasswordStrength::Weak => "weak",
+            PasswordStrength::Fair => "fair",
+            PasswordStrength::Good => "good",
+            PasswordStrength::Strong => "strong",
+        }
+    }
+}
+
+fn is_symbol(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace()
+}
+
+/// Policy parsed from the `password_policy` config, falling back to the one built in from `.env`
+/// (checked by `build.rs`) when it's invalid
+pub fn password_policy(config: &AppConfig) -> PasswordPolicy {
+    config.password_policy.parse().unwrap_or_else(|err: String| {
+        warn!("{err}");
+        BUILD_CONFIG.password_policy.parse().unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_policy() {
+        let policy: PasswordPolicy =
+            "min_length=8, lowercase,uppercase,digits,symbols,no_email".parse().unwrap();
+        assert_eq!(
+            policy,
+            PasswordPolicy {
+                min_length: 8,
+                lowercase: true,
+                uppercase: true,
+                digits: true,
+                symbols: true,
+                no_email: true,
+            }
+        );
+        assert_eq!("".parse::<PasswordPolicy>(), Ok(PasswordPolicy::default()));
+    }
+
+    #[test]
+    fn rejects_invalid_policy() {
+        assert!("min_lenght=12".parse::<PasswordPolicy>().is_err());
+        assert!("min_length=twelve".parse::<PasswordPolicy>().is_err());
+        assert!("digits=true".parse::<PasswordPolicy>().is_err());
+    }
+
+    #[test]
+    fn lists_issues() {
+        let policy: PasswordPolicy = "min_length=8,uppercase,digits,no_email".parse().unwrap();
+        assert_eq!(
+            policy.issues("jane123", "jane@example.com"),
+            vec![
+                PasswordIssue::TooShort(8),
+                PasswordIssue::MissingUppercase,
+                PasswordIssue::ContainsEmail,
+            ]
+        );
+        assert!(policy.issues("Correct horse 42", "jane@example.com").is_empty());
+        // Length counts characters, not bytes
+        assert!(PasswordPolicy::default().issues("éééééé", "").is_empty());
+    }
+
+    #[test]
+    fn ignores_short_email_names() {
+        let policy: PasswordPolicy = "no_email".parse().unwrap();
+        assert!(policy.issues("alphabet", "a@example.com").is_empty());
+        assert!(policy.issues("Johnny-B-Goode", "jo@example.com").is_empty());
+        assert_eq!(
+            policy.issues("my-john-password", "john@example.com"),
+            vec![PasswordIssue::ContainsEmail]
+        );
+    }
+
+    #[test]
+    fn maps_gotrue_reasons() {
+        let policy = PasswordPolicy { min_length: 10, ..Default::default() };
+        let reasons = ["length", "characters", "pwned", "unknown"].map(String::from);
+        assert_eq!(
+            policy.issues_from_reasons(&reasons),
+            vec![
+                PasswordIssue::TooShort(10),
+                PasswordIssue::MissingCharacters,
+                PasswordIssue::Pwned,
+            ]
+        );
+    }
+
+    #[test]
+    fn scores_strength() {
+        assert_eq!(PasswordStrength::of(""), PasswordStrength::Weak);
+        assert_eq!(PasswordStrength::of("password"), PasswordStrength::Weak);
+        assert_eq!(PasswordStrength::of("Password1"), PasswordStrength::Fair);
+        assert_eq!(PasswordStrength::of("Password1234"), PasswordStrength::Good);
+        assert_eq!(PasswordStrength::of("Correct-Horse-42!"), PasswordStrength::Strong);
+    }
+
+    #[test]
+    fn falls_back_to_built_in_policy() {
+        let config =
+            AppConfig { password_policy: "min_length=eight".to_string(), ..Default::default() };
+        assert_eq!(password_policy(&config), BUILD_CONFIG.password_policy.parse().unwrap());
+    }
+}
@@ -0,0 +1,36 @@
+use base64::{self, Engine};
+use js_sys::{ArrayBuffer, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::core::helper::local_storage;
+
+/// OAuth redirects use the PKCE authorization code flow, enabled by the `pkce` feature
+pub const ENABLED: bool = cfg!(feature = "pkce");
+
+const CODE_VERIFIER_KEY: &str = "pkce_code_verifier";
+
+/// Creates and stores a new code verifier and returns its `S256` code challenge
+pub async fn new_code_challenge() -> Result<String, JsValue> {
+    let crypto = web_sys::window().expect("Can't access to the window").crypto()?;
+    let mut random_bytes = [0u8; 32];
+    crypto.get_random_values_with_u8_array(&mut random_bytes)?;
+    let code_verifier = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(random_bytes);
+
+    let mut verifier_bytes = code_verifier.clone().into_bytes();
+    let digest = crypto.subtle().digest_with_str_and_u8_array("SHA-256", &mut verifier_bytes)?;
+    let digest = JsFuture::from(digest).await?.dyn_into::<ArrayBuffer>()?;
+    let code_challenge =
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Uint8Array::new(&digest).to_vec());
+
+    local_storage().set_item(CODE_VERIFIER_KEY, &code_verifier)?;
+    Ok(code_challenge)
+}
+
+/// Takes the code verifier stored before the redirect, it can only be used once
+pub fn take_code_verifier() -> Option<String> {
+    let storage = local_storage();
+    let code_verifier = storage.get_item(CODE_VERIFIER_KEY).ok()??;
+    storage.remove_item(CODE_VERIFIER_KEY).expect("Can't access to local storage");
+    Some(code_verifier)
+}
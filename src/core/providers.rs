@@ -0,0 +1,94 @@
+use std::str::FromStr;
+
+use leptos::logging::warn;
+
+use crate::core::config::AppConfig;
+
+/// OAuth providers supported by Supabase auth
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OAuthProvider {
+    Apple,
+    Azure,
+    Bitbucket,
+    Discord,
+    Facebook,
+    GitHub,
+    GitLab,
+    Google,
+    LinkedIn,
+    Slack,
+    Spotify,
+    Twitch,
+    Twitter,
+}
+
+impl OAuthProvider {
+    /// The `provider` query value of GoTrue `/authorize`
+    pub fn id(&self) -> &'static str {
+        match self {
+            OAuthProvider::Apple => "apple",
+            OAuthProvider::Azure => "azure",
+            OAuthProvider::Bitbucket => "bitbucket",
+            OAuthProvider::Discord => "discord",
+            OAuthProvider::Facebook => "facebook",
+            OAuthProvider::GitHub => "github",
+            OAuthProvider::GitLab => "gitlab",
+            OAuthProvider::Google => "google",
+            OAuthProvider::LinkedIn => "linkedin_oidc",
+            OAuthProvider::Slack => "slack_oidc",
+            OAuthProvider::Spotify => "spotify",
+            OAuthProvider::Twitch => "twitch",
+            OAuthProvider::Twitter => "twitter",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OAuthProvider::Apple => "Apple",
+            OAuthProvider::Azure => "Azure",
+            OAuthProvider::Bitbucket => "Bitbucket",
+            OAuthProvider::Discord => "Discord",
+            OAuthProvider::Facebook => "Facebook",
+            OAuthProvider::GitHub => "GitHub",
+            OAuthProvider::GitLab => "GitLab",
+            OAuthProvider::Google => "Google",
+            OAuthProvider::LinkedIn => "LinkedIn",
+            OAuthProvider::Slack => "Slack",
+            OAuthProvider::Spotify => "Spotify",
+            OAuthProvider::Twitch => "Twitch",
+            OAuthProvider::Twitter => "Twitter",
+        }
+    }
+}
+
+impl FromStr for OAuthProvider {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "apple" => Ok(OAuthProvider::Apple),
+            "azure" => Ok(OAuthProvider::Azure),
+            "bitbucket" => Ok(OAuthProvider::Bitbucket),
+            "discord" => Ok(OAuthProvider::Discord),
+            "facebook" => Ok(OAuthProvider::Facebook),
+            "github" => Ok(OAuthProvider::GitHub),
+            "gitlab" => Ok(OAuthProvider::GitLab),
+            "google" => Ok(OAuthProvider::Google),
+            "linkedin" | "linkedin_oidc" => Ok(OAuthProvider::LinkedIn),
+            "slack" | "slack_oidc" => Ok(OAuthProvider::Slack),
+            "spotify" => Ok(OAuthProvider::Spotify),
+            "twitch" => Ok(OAuthProvider::Twitch),
+            "twitter" => Ok(OAuthProvider::Twitter),
+            other => Err(format!("Unknown OAuth provider: {other}")),
+        }
+    }
+}
+
+/// Providers enabled by the comma separated `oauth_providers` config, in the given order
+pub fn enabled_providers(config: &AppConfig) -> Vec<OAuthProvider> {
+    config
+        .oauth_providers
+        .split(',')
+        .filter(|f| !f.trim().is_empty())
+        .filter_map(|f| f.parse().map_err(|err: String| warn!("{err}")).ok())
+        .collect()
+}
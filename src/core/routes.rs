@@ -0,0 +1,84 @@
+use crate::core::helper::percent_encode;
+
+/// Pages of the app, navigating through these keeps the targets checked at compile time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppRoute {
+    Home,
+    Login,
+    SignUp,
+    SignUpConfirmation,
+    RecoverConfirmation,
+    Verify,
+    ResetPassword,
+    Redirect,
+    MfaChallenge,
+    MfaEnroll,
+    Settings,
+}
+
+impl AppRoute {
+    /// Path below the base path, as matched by `<Route path>`
+    pub fn path(&self) -> &'static str {
+        match self {
+            AppRoute::Home => "/",
+            AppRoute::Login => "/login",
+            AppRoute::SignUp => "/signup",
+            AppRoute::SignUpConfirmation => "/signup/confirmation",
+            AppRoute::RecoverConfirmation => "/recover/confirmation",
+            AppRoute::Verify => "/verify",
+            AppRoute::ResetPassword => "/reset-password",
+            AppRoute::Redirect => "/redirect",
+            AppRoute::MfaChallenge => "/mfa/challenge",
+            AppRoute::MfaEnroll => "/mfa/enroll",
+            AppRoute::Settings => "/settings",
+        }
+    }
+
+    /// Full path including the base path, for `use_navigate` and `<Redirect>`
+    pub fn href(&self) -> String {
+        format!("{}{}", base_path(), self.path())
+    }
+
+    /// `href` with the given query parameters, empty ones are left out
+    pub fn href_with(&self, query: &[(&str, &str)]) -> String {
+        let query = query
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| format!("{key}={}", percent_encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+        if query.is_empty() {
+            return self.href();
+        }
+        format!("{}?{query}", self.href())
+    }
+}
+
+/// Path the app is served under without the trailing slash, e.g. `/csr-example-leptos-supabase`.
+/// It's taken from the `<base href>` Trunk writes from its `public_url`, so it's empty when the
+/// app is served from the root.
+pub fn base_path() -> String {
+    let Some(document) = web_sys::window().and_then(|f| f.document()) else {
+        return String::new();
+    };
+    let Some(href) = document
+        .query_selector("base[href]")
+        .ok()
+        .flatten()
+        .and_then(|base| base.get_attribute("href"))
+    else {
+        return String::new();
+    };
+    let origin = document.location().and_then(|f| f.origin().ok()).unwrap_or_default();
+    web_sys::Url::new_with_base(&href, &origin)
+        .map(|url| url.pathname().trim_end_matches('/').to_string())
+        .unwrap_or_default()
+}
+
+/// Whether the path belongs to this app, e.g. a `next` query that's safe to navigate to
+pub fn is_app_path(path: &str) -> bool {
+    let base_path = base_path();
+    path.strip_prefix(&base_path).is_some_and(|f| f.starts_with('/'))
+        && !path.contains("//")
+        && !path.contains('\\')
+}
@@ -0,0 +1,190 @@
+use leptos::{
+    ev, leptos_dom::logging::console_error, spawn_local, window, Effect, Memo, RwSignal, Signal,
+    SignalGet, SignalSet, SignalWith, SignalWithUntracked, StoredValue,
+};
+use leptos_use::use_event_listener;
+use web_sys::Storage;
+
+use crate::core::{auth_service::AuthState, helper::local_storage, models::User, token_crypto};
+
+const USER_KEY: &str = "user";
+const REMEMBER_ME_KEY: &str = "remember_me";
+/// Set in sessionStorage while the session in localStorage is the one this tab wrote
+const WROTE_LOCAL_KEY: &str = "wrote_local_session";
+
+#[inline]
+pub fn session_storage() -> Storage {
+    web_sys::window()
+        .expect("Can't access to the window")
+        .session_storage()
+        .expect("Can't access to session storage")
+        .expect("Can't access to session storage")
+}
+
+/// Whether the session is kept in localStorage, otherwise it only lives as long as the tab
+pub fn remember_me() -> bool {
+    session_storage().get_item(REMEMBER_ME_KEY).ok().flatten().as_deref() != Some("false")
+}
+
+/// Chosen on the login page, before the session arrives from a form or a redirect
+pub fn set_remember_me(remember: bool) {
+    session_storage()
+        .set_item(REMEMBER_ME_KEY, &remember.to_string())
+        .expect("Can't access to session storage");
+}
+
+/// Keeps the session of the auth state in the storage chosen with "Remember me", starting with
+/// the stored one. The returned flag turns true once it's read, which takes a moment when the
+/// tokens are encrypted.
+pub fn sync_session(state: RwSignal<AuthState>) -> Signal<bool> {
+    let loaded = RwSignal::new(false);
+    if token_crypto::ENABLED {
+        spawn_local(async move {
+            state.set(AuthState::from_user(stored_user().await));
+            loaded.set(true);
+        });
+    } else {
+        state.set(AuthState::from_user(upgrade_claims(read_user())));
+        loaded.set(true);
+    }
+    // Only changes of the session are written, not e.g. the start of a refresh
+    let user = Memo::new(move |_| state.with(|state| state.user().cloned().unwrap_or_default()));
+    // Encrypting takes a while, so only the latest user is written
+    let latest_write = StoredValue::new(0u64);
+    Effect::new(move |_| {
+        let user = user.get();
+        if !loaded.get() {
+            return;
+        }
+        if token_crypto::ENABLED {
+            latest_write.update_value(|f| *f += 1);
+            let write = latest_write.get_value();
+            spawn_local(async move {
+                let user = encrypt_tokens(user).await;
+                if latest_write.get_value() == write {
+                    write_user(&user);
+                }
+            });
+        } else {
+            write_user(&user);
+        }
+    });
+    // Logging in or out in another tab, clearing the storage comes without a key
+    let _ = use_event_listener(window(), ev::storage, move |event| {
+        if event.key().is_none_or(|key| key == USER_KEY) && remember_me() {
+            spawn_local(async move {
+                // Encrypted writes always differ, writing back an unchanged user would ping-pong
+                let new_user = stored_user().await;
+                if user.with_untracked(|user| *user != new_user) {
+                    state.set(AuthState::from_user(new_user));
+                }
+            });
+        }
+    });
+    loaded.into()
+}
+
+/// The user as it is stored right now, possibly written by another tab
+pub async fn stored_user() -> User {
+    let user = read_user();
+    if token_crypto::ENABLED {
+        upgrade_claims(decrypt_tokens(user).await)
+    } else {
+        upgrade_claims(user)
+    }
+}
+
+/// Writes the user right away, e.g. while holding a lock other tabs read it under
+pub async fn store_user(user: User) {
+    if token_crypto::ENABLED {
+        write_user(&encrypt_tokens(user).await);
+    } else {
+        write_user(&user);
+    }
+}
+
+fn read_user() -> User {
+    [session_storage(), local_storage()]
+        .iter()
+        .find_map(|storage| {
+            let json = storage.get_item(USER_KEY).ok()??;
+            serde_json::from_str(&json).ok()
+        })
+        .unwrap_or_default()
+}
+
+/// localStorage is shared with the other tabs, so a tab that doesn't remember the session only
+/// removes the session there when it wrote it itself, i.e. when "Remember me" was unchecked since
+fn write_user(user: &User) {
+    let (local, session) = (local_storage(), session_storage());
+    let json = serde_json::to_string(user).ok().filter(|_| !user.access_token.is_empty());
+    if remember_me() {
+        session.remove_item(USER_KEY).expect("Can't access to storage");
+        match json {
+            Some(json) => {
+                local.set_item(USER_KEY, &json).expect("Can't access to storage");
+                session.set_item(WROTE_LOCAL_KEY, "true").expect("Can't access to storage");
+            }
+            None => {
+                local.remove_item(USER_KEY).expect("Can't access to storage");
+                session.remove_item(WROTE_LOCAL_KEY).expect("Can't access to storage");
+            }
+        }
+    } else {
+        if session.get_item(WROTE_LOCAL_KEY).ok().flatten().is_some() {
+            local.remove_item(USER_KEY).expect("Can't access to storage");
+            session.remove_item(WROTE_LOCAL_KEY).expect("Can't access to storage");
+        }
+        match json {
+            Some(json) => session.set_item(USER_KEY, &json).expect("Can't access to storage"),
+            None => session.remove_item(USER_KEY).expect("Can't access to storage"),
+        }
+    }
+}
+
+/// Users stored by older versions don't have the decoded claims yet
+fn upgrade_claims(user: User) -> User {
+    if user.claims.sub.is_empty() && !user.access_token.is_empty() {
+        User::from_tokens(user.access_token, user.refresh_token).unwrap_or_default()
+    } else {
+        user
+    }
+}
+
+async fn encrypt_tokens(mut user: User) -> User {
+    if user.access_token.is_empty() {
+        return user;
+    }
+    match (
+        token_crypto::encrypt(&user.access_token).await,
+        token_crypto::encrypt(&user.refresh_token).await,
+    ) {
+        (Ok(access_token), Ok(refresh_token)) => {
+            user.access_token = access_token;
+            user.refresh_token = refresh_token;
+            user
+        }
+        // Never falls back to storing the tokens in plain text
+        (Err(err), _) | (_, Err(err)) => {
+            console_error(format!("Encrypting the session failed: {err:?}").as_str());
+            User::default()
+        }
+    }
+}
+
+/// Tokens stored before the encryption was enabled are taken as they are
+async fn decrypt_tokens(mut user: User) -> User {
+    for token in [&mut user.access_token, &mut user.refresh_token] {
+        if !token_crypto::is_encrypted(token) {
+            continue;
+        }
+        match token_crypto::decrypt(token).await {
+            Ok(decrypted) => *token = decrypted,
+            Err(err) => {
+                console_error(format!("Decrypting the session failed: {err:?}").as_str());
+                return User::default();
+            }
+        }
+    }
+    user
+}
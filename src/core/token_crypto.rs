@@ -0,0 +1,125 @@
+use std::cell::RefCell;
+
+use base64::{self, Engine};
+use js_sys::{Array, Promise, Uint8Array};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AesGcmParams, AesKeyGenParams, CryptoKey, IdbDatabase, IdbRequest, IdbTransactionMode,
+};
+
+/// Persisted session tokens are encrypted at rest, enabled by the `encrypted-storage` feature
+pub const ENABLED: bool = cfg!(feature = "encrypted-storage");
+
+/// Marks encrypted values, anything else is a token stored before the feature was enabled
+const ENCRYPTED_PREFIX: &str = "aes-gcm:";
+
+const DB_NAME: &str = "csr-example-leptos-supabase";
+const KEY_STORE: &str = "keys";
+const KEY_ID: &str = "session";
+
+thread_local! {
+    static KEY: RefCell<Option<CryptoKey>> = const { RefCell::new(None) };
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Encrypts the value with the key of this browser profile, with a fresh IV each time
+pub async fn encrypt(value: &str) -> Result<String, JsValue> {
+    let key = key().await?;
+    let crypto = web_sys::window().expect("Can't access to the window").crypto()?;
+    let mut iv = [0u8; 12];
+    crypto.get_random_values_with_u8_array(&mut iv)?;
+    let params = AesGcmParams::new("AES-GCM", &Uint8Array::from(&iv[..]));
+    let mut data = value.as_bytes().to_vec();
+    let encrypted = crypto.subtle().encrypt_with_object_and_u8_array(&params, &key, &mut data)?;
+    let encrypted = Uint8Array::new(&JsFuture::from(encrypted).await?).to_vec();
+    let engine = base64::engine::general_purpose::STANDARD_NO_PAD;
+    Ok(format!("{ENCRYPTED_PREFIX}{}.{}", engine.encode(iv), engine.encode(encrypted)))
+}
+
+/// Decrypts a value made by `encrypt`, it fails when the key is gone (e.g. site data cleared)
+pub async fn decrypt(value: &str) -> Result<String, JsValue> {
+    let engine = base64::engine::general_purpose::STANDARD_NO_PAD;
+    let (iv, encrypted) = value
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .and_then(|f| f.split_once('.'))
+        .ok_or("Value is not encrypted")?;
+    let iv = engine.decode(iv).map_err(|err| err.to_string())?;
+    let mut encrypted = engine.decode(encrypted).map_err(|err| err.to_string())?;
+    let key = key().await?;
+    let crypto = web_sys::window().expect("Can't access to the window").crypto()?;
+    let params = AesGcmParams::new("AES-GCM", &Uint8Array::from(&iv[..]));
+    let decrypted =
+        crypto.subtle().decrypt_with_object_and_u8_array(&params, &key, &mut encrypted)?;
+    let decrypted = Uint8Array::new(&JsFuture::from(decrypted).await?).to_vec();
+    String::from_utf8(decrypted).map_err(|err| err.to_string().into())
+}
+
+/// The non-extractable AES-GCM key kept in IndexedDB, created on first use
+async fn key() -> Result<CryptoKey, JsValue> {
+    if let Some(key) = KEY.with_borrow(Clone::clone) {
+        return Ok(key);
+    }
+    let db = open_db().await?;
+    let key = match stored_key(&db).await? {
+        Some(key) => key,
+        None => {
+            let crypto = web_sys::window().expect("Can't access to the window").crypto()?;
+            let usages = Array::of2(&"encrypt".into(), &"decrypt".into());
+            let new_key = crypto.subtle().generate_key_with_object(
+                &AesKeyGenParams::new("AES-GCM", 256),
+                false,
+                &usages,
+            )?;
+            let new_key = JsFuture::from(new_key).await?.dyn_into::<CryptoKey>()?;
+            let store = db
+                .transaction_with_str_and_mode(KEY_STORE, IdbTransactionMode::Readwrite)?
+                .object_store(KEY_STORE)?;
+            // Adding fails when another tab stored its key first, which everyone has to use then
+            match wait_for(&store.add_with_key(&new_key, &KEY_ID.into())?).await {
+                Ok(_) => new_key,
+                Err(_) => stored_key(&db).await?.ok_or("Can't store the encryption key")?,
+            }
+        }
+    };
+    KEY.set(Some(key.clone()));
+    Ok(key)
+}
+
+async fn stored_key(db: &IdbDatabase) -> Result<Option<CryptoKey>, JsValue> {
+    let store = db
+        .transaction_with_str_and_mode(KEY_STORE, IdbTransactionMode::Readonly)?
+        .object_store(KEY_STORE)?;
+    Ok(wait_for(&store.get(&KEY_ID.into())?).await?.dyn_into::<CryptoKey>().ok())
+}
+
+async fn open_db() -> Result<IdbDatabase, JsValue> {
+    let request = web_sys::window()
+        .expect("Can't access to the window")
+        .indexed_db()?
+        .ok_or("IndexedDB is not available")?
+        .open_with_u32(DB_NAME, 1)?;
+    let upgrading_request = request.clone();
+    let on_upgrade_needed = Closure::<dyn FnMut(JsValue)>::new(move |_| {
+        if let Ok(db) = upgrading_request.result().and_then(|f| f.dyn_into::<IdbDatabase>()) {
+            db.create_object_store(KEY_STORE).ok();
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade_needed.as_ref().unchecked_ref()));
+    let db = wait_for(&request).await?.dyn_into::<IdbDatabase>()?;
+    request.set_onupgradeneeded(None);
+    Ok(db)
+}
+
+/// Resolves with the result of the IndexedDB request
+async fn wait_for(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let done = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    JsFuture::from(done).await?;
+    request.result()
+}
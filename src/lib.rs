@@ -1,6 +1,7 @@
-use app::App;
-use leptos::mount_to_body;
+use app::{App, ConfigError};
+use leptos::{logging::error, mount_to_body, view};
 use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen_futures::spawn_local;
 mod app;
 mod components;
 mod core;
@@ -10,6 +11,14 @@ mod env;
 pub fn start() {
     console_log::init_with_level(log::Level::Debug).unwrap_or_default();
     console_error_panic_hook::set_once();
-    mount_to_body(App);
+    spawn_local(async {
+        match core::config::load_config().await {
+            Ok(config) => mount_to_body(move || view! { <App config=config/> }),
+            Err(err) => {
+                error!("Invalid app config: {err}");
+                mount_to_body(move || view! { <ConfigError error=err/> });
+            }
+        }
+    });
 }
 